@@ -1,16 +1,27 @@
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::time::Duration;
 
-use clap::{Args, Parser, Subcommand, ValueEnum};
-use serde::{Deserialize, Serialize};
-use strum_macros::Display;
+use crate::checkpoint_server::BindAddress;
+use crate::client::{RequestConfig, StateId, SyncCheck, MAX_RETRIES};
+use crate::processor::OutputFormat;
+use crate::quorum::QuorumPolicy;
+use crate::wss::BeaconClient;
+use clap::{Args, Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about)]
 pub struct Cli {
     #[command(flatten)]
     pub shared: SharedCommands,
-    #[arg(long, short, value_enum)]
-    pub network: Option<Network>,
+    #[arg(
+        long,
+        short,
+        global = true,
+        default_value = "mainnet",
+        help = "Name of the network, either built-in (mainnet, goerli, sepolia, holesky, hoodi, gnosis) or defined in the config file"
+    )]
+    pub network: String,
     #[arg(
         short,
         long,
@@ -18,6 +29,31 @@ pub struct Cli {
         help = "Display verbose result or not"
     )]
     pub verbose: bool,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Establish quorum over the finalized, current justified and previous justified checkpoints"
+    )]
+    pub all_checkpoints: bool,
+    #[arg(
+        long,
+        default_value = "text",
+        help = "Output format: json or yaml for the full result, text for coloured text, table for aligned columns or plain for key=value lines"
+    )]
+    pub output: OutputFormat,
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with = "all_checkpoints",
+        help = "Print only the weak subjectivity checkpoint, as 0xroot:epoch"
+    )]
+    pub wss: bool,
+    #[arg(
+        long,
+        conflicts_with = "all_checkpoints",
+        help = "Print the checkpoint sync and weak subjectivity flags for a client: lighthouse, prysm, teku, nimbus or lodestar"
+    )]
+    pub client: Option<BeaconClient>,
     #[command(subcommand)]
     pub subcommands: Option<SubCommands>,
 }
@@ -26,6 +62,21 @@ pub struct Cli {
 pub enum SubCommands {
     #[command(about = "Run in server mode", name = "server")]
     ServerCliCommands(ServerCommands),
+    #[command(
+        about = "Download the finalized state and block, checking their roots against the checkpoint",
+        name = "download"
+    )]
+    DownloadCliCommands(DownloadCommands),
+    #[command(
+        about = "Verify a local SSZ encoded state against the finalized checkpoint",
+        name = "verify-state"
+    )]
+    VerifyStateCliCommands(VerifyStateCommands),
+    #[command(
+        about = "Poll the providers every epoch and report how finality moves",
+        name = "watch"
+    )]
+    WatchCliCommands(WatchCommands),
 }
 
 #[derive(Args)]
@@ -39,6 +90,73 @@ pub struct ServerCommands {
         help = "Port for HTTP server. Defaults to 7070"
     )]
     pub port: u16,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Fetch the finalized checkpoint from the providers on every request, instead of serving the result cached and refreshed every epoch"
+    )]
+    pub no_cache: bool,
+    #[arg(
+        long,
+        help = "Seconds after which the last quorum result of a network no longer counts toward /ready. Defaults to two epochs of the network"
+    )]
+    pub ready_max_age: Option<u64>,
+    #[arg(
+        long,
+        help = "Address to listen on: an IPv4 or IPv6 address, listened on with --port, or a socket address like [::1]:7070. Can be repeated. Defaults to 127.0.0.1"
+    )]
+    pub bind: Vec<BindAddress>,
+    #[arg(long, help = "Path of a Unix socket to listen on")]
+    pub unix_socket: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Socket address serving /metrics, /health and /ready apart from the API, like 127.0.0.1:9090. Can be repeated"
+    )]
+    pub admin_bind: Vec<SocketAddr>,
+}
+
+#[derive(Args)]
+pub struct DownloadCommands {
+    #[command(flatten)]
+    pub shared: SharedCommands,
+    #[arg(
+        long,
+        default_value = "state.ssz",
+        help = "Path to write the SSZ encoded finalized state to"
+    )]
+    pub state_file: PathBuf,
+    #[arg(
+        long,
+        default_value = "block.ssz",
+        help = "Path to write the SSZ encoded finalized block to"
+    )]
+    pub block_file: PathBuf,
+    #[arg(
+        long,
+        default_value_t = 300,
+        help = "Seconds to wait for the state or block download from a single endpoint"
+    )]
+    pub download_timeout: u64,
+}
+
+#[derive(Args)]
+pub struct VerifyStateCommands {
+    #[command(flatten)]
+    pub shared: SharedCommands,
+    #[arg(help = "Path to the SSZ encoded state")]
+    pub file: PathBuf,
+}
+
+#[derive(Args)]
+pub struct WatchCommands {
+    #[command(flatten)]
+    pub shared: SharedCommands,
+    #[arg(
+        long,
+        default_value_t = 4,
+        help = "Number of epochs the finalized epoch may lag the wall clock by before finality is reported as stalled"
+    )]
+    pub stall_epochs: u64,
 }
 
 #[derive(Args)]
@@ -49,14 +167,75 @@ pub struct SharedCommands {
         help = "Path to config file where endpoints for network are listed. default is ./endpoint.yaml"
     )]
     pub endpoints: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Quorum policy: endpoints:<n>/<d>, responders:<n>/<d>, min:<count> or unanimous. Overrides the config file. default is endpoints:2/3"
+    )]
+    pub quorum: Option<QuorumPolicy>,
+    #[arg(
+        long,
+        default_value = "finalized",
+        help = "State to read the finality checkpoints from: finalized, head, justified, genesis, a slot number or a 0x prefixed state root"
+    )]
+    pub state_id: StateId,
+    #[arg(
+        long,
+        default_value_t = 5,
+        help = "Seconds to wait for a connection to an endpoint"
+    )]
+    pub connect_timeout: u64,
+    #[arg(
+        long,
+        default_value_t = 10,
+        help = "Seconds to wait for a single request to an endpoint"
+    )]
+    pub request_timeout: u64,
+    #[arg(
+        long,
+        default_value_t = 30,
+        help = "Seconds to wait for all endpoints, including retries, before giving up on the slow ones"
+    )]
+    pub deadline: u64,
+    #[arg(
+        long,
+        default_value_t = 2,
+        value_parser = clap::value_parser!(u32).range(..=i64::from(MAX_RETRIES)),
+        help = "Number of retries for timeouts, connection errors and 5xx or 429 responses, at most 10"
+    )]
+    pub retries: u32,
+    #[arg(
+        long,
+        default_value_t = 250,
+        help = "Milliseconds to wait before the first retry, doubled on every further retry"
+    )]
+    pub retry_backoff: u64,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Stop waiting for endpoints once quorum is decided"
+    )]
+    pub early_exit: bool,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Count the votes of providers without checking their genesis validators root and fork version against the network"
+    )]
+    pub skip_network_check: bool,
+    #[arg(
+        long,
+        default_value = "off",
+        help = "Check the sync status of providers first: off, flag the syncing, optimistic or el offline ones, or drop them from the vote"
+    )]
+    pub sync_check: SyncCheck,
 }
 
-#[derive(
-    Display, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug, Deserialize, Serialize,
-)]
-#[serde(rename_all = "lowercase")]
-pub enum Network {
-    Mainnet,
-    Goerli,
-    Sepolia,
+impl SharedCommands {
+    pub fn request_config(&self) -> RequestConfig {
+        RequestConfig {
+            request_timeout: Duration::from_secs(self.request_timeout),
+            deadline: Duration::from_secs(self.deadline),
+            max_retries: self.retries,
+            initial_backoff: Duration::from_millis(self.retry_backoff),
+        }
+    }
 }
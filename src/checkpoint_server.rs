@@ -1,16 +1,25 @@
-use crate::args::Network;
-use crate::client::CheckpointClient;
+use crate::client::{CheckpointClient, FetchOptions, HttpClient, StateId, SyncCheck};
 use crate::errors::AppError;
-use crate::processor::DisplayableResult;
+use crate::processor::{CheckpointKind, DisplayableResult, QuorumStatus, Verdict};
+use crate::quorum::QuorumPolicy;
+use axum::extract::rejection::{PathRejection, QueryRejection};
 use axum::extract::{Path, Query};
+use axum::http::header;
 use axum::response::Response;
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json, Router};
-use axum_macros::debug_handler;
+use futures::future::{BoxFuture, Shared};
+use futures::FutureExt;
 use serde::{Deserialize, Serialize};
-use std::{net::SocketAddr, sync::Arc};
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tower_http::trace::TraceLayer;
-use tracing::info;
 use tracing::level_filters::LevelFilter;
+use tracing::{info, warn};
 
 use tracing_subscriber::{fmt, EnvFilter};
 
@@ -18,12 +27,39 @@ use tracing_subscriber::{fmt, EnvFilter};
 pub struct QueryParams {
     #[serde(default)]
     pub verbose: bool,
+    pub quorum: Option<QuorumPolicy>,
+    pub state_id: Option<StateId>,
+    pub early_exit: Option<bool>,
+    pub sync_check: Option<SyncCheck>,
+}
+
+impl QueryParams {
+    fn fetch_options(&self, defaults: FetchOptions) -> FetchOptions {
+        FetchOptions {
+            state_id: self.state_id.clone().unwrap_or(defaults.state_id),
+            quorum_policy: self.quorum.unwrap_or(defaults.quorum_policy),
+            early_exit: self.early_exit.unwrap_or(defaults.early_exit),
+            sync_check: self.sync_check.unwrap_or(defaults.sync_check),
+        }
+    }
+}
+
+// How old a result served from the cache is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheInfo {
+    // seconds since the result was fetched
+    pub age: u64,
+    // unix time in seconds of the last refresh that reached a canonical checkpoint
+    pub last_successful_refresh: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiResponse {
-    block_root: String,
-    epoch: String,
+    #[serde(flatten)]
+    verdict: Verdict,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(flatten)]
+    cache: Option<CacheInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(flatten)]
     payload: Option<DisplayableResult>,
@@ -35,94 +71,744 @@ impl IntoResponse for ApiResponse {
     }
 }
 
-impl IntoResponse for AppError {
+// Error body returned with every non 2xx response. The code is meant to be matched on by
+// clients, the message to be read by humans.
+#[derive(Debug, Serialize)]
+pub struct ApiError {
+    #[serde(skip)]
+    status_code: StatusCode,
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(flatten)]
+    cache: Option<CacheInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(flatten)]
+    payload: Option<DisplayableResult>,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status_code, Json(self)).into_response()
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(error: AppError) -> Self {
+        let (status_code, code) = match error {
+            AppError::EndpointsNotFound { .. } | AppError::UnknownNetwork { .. } => {
+                (StatusCode::BAD_REQUEST, "unknown_network")
+            }
+            AppError::InvalidQuorumPolicy { .. }
+            | AppError::InvalidStateId { .. }
+            | AppError::InvalidSyncCheck { .. } => (StatusCode::BAD_REQUEST, "invalid_parameter"),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+        };
+        ApiError {
+            status_code,
+            code,
+            message: error.to_string(),
+            cache: None,
+            payload: None,
+        }
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        ApiError {
+            status_code: StatusCode::BAD_REQUEST,
+            code: "unknown_network",
+            message: rejection.body_text(),
+            cache: None,
+            payload: None,
+        }
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError {
+            status_code: StatusCode::BAD_REQUEST,
+            code: "invalid_parameter",
+            message: rejection.body_text(),
+            cache: None,
+            payload: None,
+        }
+    }
+}
+
+// Whether a recent quorum result exists for every configured network, returned by /ready with
+// a 200 status when it does and a 503 otherwise
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub networks: BTreeMap<String, NetworkReadiness>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkReadiness {
+    pub ready: bool,
+    // seconds since the last canonical checkpoint fetched with the default options, if any
+    pub age: Option<u64>,
+}
+
+impl IntoResponse for Readiness {
     fn into_response(self) -> Response {
-        (StatusCode::NOT_FOUND, self.to_string()).into_response()
+        let status_code = if self.ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        (status_code, Json(self)).into_response()
     }
 }
 
-#[derive(Debug)]
-pub struct CheckPointMiddleware {
-    checkpoint_client: CheckpointClient<reqwest::Client>,
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Health {
+    pub status: String,
+}
+
+// An address given to --bind: an IPv4 or IPv6 address listened on with the port of the server,
+// or a socket address with its own port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BindAddress {
+    pub ip: IpAddr,
+    pub port: Option<u16>,
+}
+
+impl BindAddress {
+    pub fn socket_addr(&self, default_port: u16) -> SocketAddr {
+        SocketAddr::new(self.ip, self.port.unwrap_or(default_port))
+    }
+}
+
+impl Default for BindAddress {
+    fn default() -> Self {
+        BindAddress {
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: None,
+        }
+    }
+}
+
+impl std::fmt::Display for BindAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.port {
+            Some(port) => write!(f, "{}", SocketAddr::new(self.ip, port)),
+            None => write!(f, "{}", self.ip),
+        }
+    }
+}
+
+impl FromStr for BindAddress {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(addr) = s.parse::<SocketAddr>() {
+            return Ok(BindAddress {
+                ip: addr.ip(),
+                port: Some(addr.port()),
+            });
+        }
+        // IPv6 addresses may be bracketed without a port, as in [::1]
+        let ip = s
+            .strip_prefix('[')
+            .and_then(|ip| ip.strip_suffix(']'))
+            .unwrap_or(s);
+        ip.parse::<IpAddr>()
+            .map(|ip| BindAddress { ip, port: None })
+            .map_err(|_| AppError::InvalidBindAddress {
+                message: format!(
+                    "Invalid bind address {s}. Expected an IP address, like 0.0.0.0 or ::, or a socket address, like [::1]:7070"
+                ),
+            })
+    }
+}
+
+// Where the server accepts connections
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Listener {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl std::fmt::Display for Listener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Listener::Tcp(addr) => write!(f, "{addr}"),
+            Listener::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl Listener {
+    // Serves the router until ctrl-c or SIGTERM is received
+    pub async fn serve(self, router: Router) -> Result<(), AppError> {
+        let listen_error = |error: &dyn std::fmt::Display| AppError::Listen {
+            address: self.to_string(),
+            message: error.to_string(),
+        };
+        match &self {
+            Listener::Tcp(addr) => axum::Server::try_bind(addr)
+                .map_err(|e| listen_error(&e))?
+                .serve(router.into_make_service())
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+                .map_err(|e| listen_error(&e)),
+            #[cfg(unix)]
+            Listener::Unix(path) => {
+                use std::os::unix::fs::FileTypeExt;
+                // a socket left behind by a previous run would make the bind fail, but one another
+                // server still accepts connections on is not taken over
+                if std::fs::symlink_metadata(path)
+                    .map(|metadata| metadata.file_type().is_socket())
+                    .unwrap_or(false)
+                {
+                    if tokio::net::UnixStream::connect(path).await.is_ok() {
+                        return Err(listen_error(&"another server is listening on the socket"));
+                    }
+                    std::fs::remove_file(path).map_err(|e| listen_error(&e))?;
+                }
+                let listener =
+                    tokio::net::UnixListener::bind(path).map_err(|e| listen_error(&e))?;
+                let _socket = SocketFile(path.clone());
+                let incoming = futures::stream::unfold(listener, |listener| async move {
+                    let stream = listener.accept().await.map(|(stream, _)| stream);
+                    Some((stream, listener))
+                });
+                axum::Server::builder(hyper::server::accept::from_stream(incoming))
+                    .serve(router.into_make_service())
+                    .with_graceful_shutdown(shutdown_signal())
+                    .await
+                    .map_err(|e| listen_error(&e))
+            }
+            #[cfg(not(unix))]
+            Listener::Unix(_) => Err(listen_error(
+                &"unix sockets are not supported on this platform",
+            )),
+        }
+    }
+}
+
+// The file of a bound unix socket, removed once the server stops serving on it
+#[cfg(unix)]
+struct SocketFile(PathBuf);
+
+#[cfg(unix)]
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Err(error) = std::fs::remove_file(&self.0) {
+            warn!("removing the socket {} failed: {error}", self.0.display());
+        }
+    }
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {},
+                    _ = terminate.recv() => {},
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+// A quorum result of the finalized endpoint, fetched with the default options
+#[derive(Debug, Clone)]
+struct CachedResult {
+    result: DisplayableResult,
+    fetched_at: SystemTime,
+    // the last time a refresh reached a canonical checkpoint
+    last_success: Option<SystemTime>,
+}
+
+impl CachedResult {
+    fn info(&self) -> CacheInfo {
+        CacheInfo {
+            age: self.fetched_at.elapsed().unwrap_or_default().as_secs(),
+            last_successful_refresh: self.last_success.map(|time| {
+                time.duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+            }),
+        }
+    }
+}
+
+// A fetch of the finalized checkpoint in progress, awaited by every request made for the same
+// network and options until it completes
+type InFlight = Shared<BoxFuture<'static, Result<DisplayableResult, AppError>>>;
+
+pub struct CheckPointMiddleware<C: HttpClient> {
+    checkpoint_client: CheckpointClient<C>,
     port: u16,
+    cache_enabled: bool,
+    cache: RwLock<HashMap<(String, StateId), CachedResult>>,
+    in_flight: Mutex<HashMap<(String, FetchOptions), InFlight>>,
+    // how old the last quorum result of a network can be for the server to be ready, two epochs
+    // of the network when not set
+    ready_max_age: Option<Duration>,
+    last_quorum: RwLock<HashMap<String, SystemTime>>,
+    // the API is served on 127.0.0.1 with the port when no tcp listener is set
+    listeners: Vec<Listener>,
+    // listeners serving /metrics, /health and /ready apart from the API, when set
+    admin_listeners: Vec<Listener>,
 }
 
-impl CheckPointMiddleware {
-    pub fn new(checkpoint_client: CheckpointClient<reqwest::Client>, port: u16) -> Self {
+impl<C: HttpClient + std::fmt::Debug> std::fmt::Debug for CheckPointMiddleware<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CheckPointMiddleware")
+            .field("checkpoint_client", &self.checkpoint_client)
+            .field("port", &self.port)
+            .field("cache_enabled", &self.cache_enabled)
+            .field("cache", &self.cache)
+            .field("ready_max_age", &self.ready_max_age)
+            .field("last_quorum", &self.last_quorum)
+            .field("listeners", &self.listeners)
+            .field("admin_listeners", &self.admin_listeners)
+            .finish()
+    }
+}
+
+impl<C: HttpClient + Send + Sync + 'static> CheckPointMiddleware<C> {
+    pub fn new(checkpoint_client: CheckpointClient<C>, port: u16) -> Self {
         Self {
             checkpoint_client,
             port,
+            cache_enabled: true,
+            cache: RwLock::new(HashMap::new()),
+            in_flight: Mutex::new(HashMap::new()),
+            ready_max_age: None,
+            last_quorum: RwLock::new(HashMap::new()),
+            listeners: vec![],
+            admin_listeners: vec![],
+        }
+    }
+
+    pub fn with_cache(mut self, cache_enabled: bool) -> Self {
+        self.cache_enabled = cache_enabled;
+        self
+    }
+
+    pub fn with_ready_max_age(mut self, ready_max_age: Option<Duration>) -> Self {
+        self.ready_max_age = ready_max_age;
+        self
+    }
+
+    pub fn with_listeners(mut self, listeners: Vec<Listener>) -> Self {
+        self.listeners = listeners;
+        self
+    }
+
+    pub fn with_admin_listeners(mut self, admin_listeners: Vec<Listener>) -> Self {
+        self.admin_listeners = admin_listeners;
+        self
+    }
+
+    pub fn router(self) -> Router {
+        Self::routes(Arc::new(self))
+    }
+
+    // the router of a middleware shared with other tasks, like the background refresh
+    pub fn routes(middleware: Arc<Self>) -> Router {
+        Self::api_routes()
+            .merge(Self::admin_routes())
+            .layer(TraceLayer::new_for_http())
+            .with_state(middleware)
+    }
+
+    // the router of the API alone, for when the admin routes are served by other listeners
+    pub fn api_router(middleware: Arc<Self>) -> Router {
+        Self::api_routes()
+            .layer(TraceLayer::new_for_http())
+            .with_state(middleware)
+    }
+
+    pub fn admin_router(middleware: Arc<Self>) -> Router {
+        Self::admin_routes()
+            .layer(TraceLayer::new_for_http())
+            .with_state(middleware)
+    }
+
+    fn api_routes() -> Router<Arc<Self>> {
+        Router::new()
+            .route("/:network/finalized", axum::routing::get(finalized::<C>))
+            .route("/:network/justified", axum::routing::get(justified::<C>))
+    }
+
+    fn admin_routes() -> Router<Arc<Self>> {
+        Router::new()
+            .route("/metrics", axum::routing::get(metrics::<C>))
+            .route("/health", axum::routing::get(health))
+            .route("/ready", axum::routing::get(ready::<C>))
+    }
+
+    // Fetches the finalized checkpoint, joining the fetch already in progress for the same network
+    // and options if there is one, so concurrent requests only fan out to the providers once. The
+    // fetch runs in its own task, so it completes and leaves the map even when every request
+    // waiting for it is dropped
+    async fn fetch_coalesced(
+        self: &Arc<Self>,
+        network: &str,
+        options: &FetchOptions,
+    ) -> Result<DisplayableResult, AppError> {
+        let key = (network.to_lowercase(), options.clone());
+        let in_flight = self
+            .in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key.clone())
+            .or_insert_with(|| {
+                let middleware = self.clone();
+                let (network, options) = (network.to_string(), options.clone());
+                let fetch = tokio::spawn(async move {
+                    let result = middleware
+                        .checkpoint_client
+                        .fetch_finality_checkpoints_with_options(&network, &options)
+                        .await;
+                    if let Ok(result) = &result {
+                        middleware.record_quorum(&network, &options, result);
+                    }
+                    middleware
+                        .in_flight
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .remove(&key);
+                    result
+                });
+                async move {
+                    fetch.await.unwrap_or_else(|e| {
+                        Err(AppError::NoVerifiedCheckpoint {
+                            message: format!("Fetching the checkpoint failed: {e}"),
+                        })
+                    })
+                }
+                .boxed()
+                .shared()
+            })
+            .clone();
+        in_flight.await
+    }
+
+    // Results fetched with the default options that reached quorum make the network ready
+    fn record_quorum(&self, network: &str, options: &FetchOptions, result: &DisplayableResult) {
+        if *options == self.checkpoint_client.default_options()
+            && result.status() == QuorumStatus::Canonical
+        {
+            self.last_quorum
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(network.to_lowercase(), SystemTime::now());
+        }
+    }
+
+    pub fn readiness(&self) -> Readiness {
+        let last_quorum = self.last_quorum.read().unwrap_or_else(|e| e.into_inner());
+        let networks: BTreeMap<String, NetworkReadiness> = self
+            .checkpoint_client
+            .networks()
+            .into_iter()
+            .map(|network| {
+                let max_age = self.ready_max_age.or_else(|| {
+                    self.checkpoint_client
+                        .network(&network)
+                        .ok()
+                        .map(|config| Duration::from_secs(2 * config.seconds_per_epoch()))
+                });
+                let age = last_quorum
+                    .get(&network.to_lowercase())
+                    .map(|time| time.elapsed().unwrap_or_default());
+                let ready = matches!((age, max_age), (Some(age), Some(max_age)) if age <= max_age);
+                let age = age.map(|age| age.as_secs());
+                (network, NetworkReadiness { ready, age })
+            })
+            .collect();
+        Readiness {
+            ready: networks.values().all(|network| network.ready),
+            networks,
         }
     }
 
-    pub async fn serve(self) {
-        fmt()
+    // Fetches the result for the network and state id with the default options, and caches it
+    // for the requests that follow when the state id follows the chain
+    pub async fn refresh(
+        self: &Arc<Self>,
+        network: &str,
+        state_id: &StateId,
+    ) -> Result<(), AppError> {
+        let options = FetchOptions {
+            state_id: state_id.clone(),
+            ..self.checkpoint_client.default_options()
+        };
+        let result = self.fetch_coalesced(network, &options).await?;
+        if state_id.follows_chain() {
+            self.store(network, state_id, result);
+        }
+        Ok(())
+    }
+
+    fn store(&self, network: &str, state_id: &StateId, result: DisplayableResult) -> CachedResult {
+        let now = SystemTime::now();
+        let key = (network.to_lowercase(), state_id.clone());
+        let mut cache = self.cache.write().unwrap_or_else(|e| e.into_inner());
+        let last_success = if result.status() == QuorumStatus::Canonical {
+            Some(now)
+        } else {
+            cache.get(&key).and_then(|cached| cached.last_success)
+        };
+        let cached = CachedResult {
+            result,
+            fetched_at: now,
+            last_success,
+        };
+        cache.insert(key, cached.clone());
+        cached
+    }
+
+    // The cached result, as long as it is not older than an epoch, which it only is when the
+    // background refresh is not running or failing
+    async fn cached_result(
+        self: &Arc<Self>,
+        network: &str,
+        options: &FetchOptions,
+    ) -> Result<CachedResult, AppError> {
+        let max_age =
+            Duration::from_secs(self.checkpoint_client.network(network)?.seconds_per_epoch());
+        let key = (network.to_lowercase(), options.state_id.clone());
+        let cached = self
+            .cache
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&key)
+            .filter(|cached| cached.fetched_at.elapsed().unwrap_or_default() < max_age)
+            .cloned();
+        self.checkpoint_client
+            .metrics()
+            .observe_cache(network, cached.is_some());
+        if let Some(cached) = cached {
+            return Ok(cached);
+        }
+        let result = self.fetch_coalesced(network, options).await?;
+        Ok(self.store(network, &options.state_id, result))
+    }
+
+    // Refreshes the cached results of the network, right away and then on every epoch boundary
+    async fn refresh_on_epochs(self: Arc<Self>, network: String) {
+        let Ok(network_config) = self.checkpoint_client.network(&network) else {
+            return;
+        };
+        let default_state_id = self.checkpoint_client.default_options().state_id;
+        loop {
+            let mut state_ids: Vec<StateId> = self
+                .cache
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .keys()
+                .filter(|(cached_network, _)| *cached_network == network.to_lowercase())
+                .map(|(_, state_id)| state_id.clone())
+                .filter(StateId::follows_chain)
+                .collect();
+            if !state_ids.contains(&default_state_id) {
+                state_ids.push(default_state_id.clone());
+            }
+            for state_id in state_ids {
+                if let Err(error) = self.refresh(&network, &state_id).await {
+                    warn!("refreshing {network} {state_id} failed: {error}");
+                }
+            }
+            tokio::time::sleep(network_config.until_next_epoch()).await;
+        }
+    }
+
+    pub async fn serve(self) -> Result<(), AppError> {
+        // the subscriber is only set by the first server of the process
+        let _ = fmt()
             .with_env_filter(
                 EnvFilter::builder()
                     .with_default_directive(LevelFilter::INFO.into())
                     .from_env_lossy(),
             )
-            .init();
+            .try_init();
 
-        info!("starting server on port {}", self.port);
-        let port = self.port;
-        let app = Router::new()
-            .route("/:network/finalized", axum::routing::get(finalized))
-            .layer(TraceLayer::new_for_http())
-            .with_state(Arc::new(self));
+        // a unix socket is listened on as well as the default address, not instead of it
+        let mut listeners = self.listeners.clone();
+        if !listeners
+            .iter()
+            .any(|listener| matches!(listener, Listener::Tcp(_)))
+        {
+            listeners.push(Listener::Tcp(BindAddress::default().socket_addr(self.port)));
+        }
+        let admin_listeners = self.admin_listeners.clone();
+        let middleware = Arc::new(self);
+        // the refresh also runs with the cache turned off, to keep the server ready
+        for network in middleware.checkpoint_client.networks() {
+            tokio::spawn(middleware.clone().refresh_on_epochs(network));
+        }
+        let api = if admin_listeners.is_empty() {
+            Self::routes(middleware.clone())
+        } else {
+            Self::api_router(middleware.clone())
+        };
+        let admin = Self::admin_router(middleware);
 
-        let addr = SocketAddr::from(([127, 0, 0, 1], port));
-        axum::Server::bind(&addr)
-            .serve(app.into_make_service())
-            .await
-            .unwrap();
+        let servers = listeners
+            .into_iter()
+            .map(|listener| (listener, api.clone(), "API"))
+            .chain(
+                admin_listeners
+                    .into_iter()
+                    .map(|listener| (listener, admin.clone(), "admin")),
+            )
+            .map(|(listener, router, name)| {
+                info!("serving the {name} on {listener}");
+                listener.serve(router)
+            });
+        futures::future::try_join_all(servers).await?;
+        Ok(())
     }
 }
 
-#[debug_handler]
-async fn finalized(
-    State(middle_ware): State<Arc<CheckPointMiddleware>>,
-    Path(network): Path<Network>,
-    Query(query_params): Query<QueryParams>,
-) -> Result<Json<ApiResponse>, AppError> {
-    let displayable_result = middle_ware
-        .checkpoint_client
-        .fetch_finality_checkpoints(network)
-        .await?;
+async fn finalized<C: HttpClient + Send + Sync + 'static>(
+    State(middle_ware): State<Arc<CheckPointMiddleware<C>>>,
+    network: Result<Path<String>, PathRejection>,
+    query_params: Result<Query<QueryParams>, QueryRejection>,
+) -> Result<Json<ApiResponse>, ApiError> {
+    let Path(network) = network?;
+    let Query(query_params) = query_params?;
+    let checkpoint_client = &middle_ware.checkpoint_client;
+    let defaults = checkpoint_client.default_options();
+    let options = query_params.fetch_options(defaults.clone());
+    // only results of the state ids that follow the chain, fetched with the default options
+    // otherwise, are cached. Slots and roots would grow the cache without bound
+    let cacheable = middle_ware.cache_enabled
+        && options.state_id.follows_chain()
+        && options
+            == FetchOptions {
+                state_id: options.state_id.clone(),
+                ..defaults
+            };
+    let (displayable_result, cache) = if cacheable {
+        let cached = middle_ware.cached_result(&network, &options).await?;
+        let info = cached.info();
+        (cached.result, Some(info))
+    } else {
+        let displayable_result = middle_ware.fetch_coalesced(&network, &options).await?;
+        (displayable_result, None)
+    };
 
-    let block_not_found_msg = "Finalized block root not found";
-    let epoch_not_found_msg = "Epoch not found";
-    let (block_root, epoch) = match displayable_result.canonical.as_ref() {
-        Some(canonical) => {
-            let block_root = canonical
-                .keys()
-                .next()
-                .map(|s| s.to_string())
-                .unwrap_or(block_not_found_msg.to_string());
-            let epoch = canonical
-                .get(&block_root)
-                .and_then(|success_payloads| success_payloads.iter().next())
-                .map(|success_payload| success_payload.payload.data.finalized.epoch.to_string())
-                .unwrap_or(epoch_not_found_msg.to_string());
-            (block_root, epoch)
-        }
-        None => (
-            block_not_found_msg.to_string(),
-            epoch_not_found_msg.to_string(),
+    // anything but a canonical checkpoint is an error, so a placeholder is never mistaken for a
+    // block root
+    let (status_code, code, message) = match displayable_result.status() {
+        QuorumStatus::Canonical => {
+            return Ok(Json(to_api_response(
+                displayable_result,
+                query_params.verbose,
+                cache,
+            )))
+        }
+        QuorumStatus::Conflicting => (
+            StatusCode::CONFLICT,
+            "conflicting_checkpoints",
+            "Providers returned conflicting checkpoints and none reached quorum",
+        ),
+        QuorumStatus::Insufficient => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "insufficient_quorum",
+            "Not enough providers responded to reach quorum",
+        ),
+        QuorumStatus::Failed => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "no_response",
+            "No provider returned a checkpoint",
+        ),
+        QuorumStatus::HeaderMismatch => (
+            StatusCode::CONFLICT,
+            "header_mismatch",
+            "Providers that agreed on the checkpoint returned mismatching block headers",
         ),
     };
+    Err(ApiError {
+        status_code,
+        code,
+        message: message.to_string(),
+        cache,
+        payload: query_params.verbose.then_some(displayable_result),
+    })
+}
 
-    let payload = if query_params.verbose {
+async fn justified<C: HttpClient + Send + Sync + 'static>(
+    State(middle_ware): State<Arc<CheckPointMiddleware<C>>>,
+    network: Result<Path<String>, PathRejection>,
+    query_params: Result<Query<QueryParams>, QueryRejection>,
+) -> Result<Json<BTreeMap<CheckpointKind, ApiResponse>>, ApiError> {
+    let Path(network) = network?;
+    let Query(query_params) = query_params?;
+    let checkpoint_client = &middle_ware.checkpoint_client;
+    let options = query_params.fetch_options(checkpoint_client.default_options());
+    let displayable_results = checkpoint_client
+        .fetch_all_checkpoints_with_options(&network, &options)
+        .await?;
+
+    Ok(Json(
+        displayable_results
+            .into_iter()
+            .map(|displayable_result| {
+                (
+                    displayable_result.checkpoint,
+                    to_api_response(displayable_result, query_params.verbose, None),
+                )
+            })
+            .collect(),
+    ))
+}
+
+async fn metrics<C: HttpClient + Send + Sync + 'static>(
+    State(middle_ware): State<Arc<CheckPointMiddleware<C>>>,
+) -> Result<Response, ApiError> {
+    let body = middle_ware.checkpoint_client.metrics().encode()?;
+    Ok(([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body).into_response())
+}
+
+async fn health() -> Json<Health> {
+    Json(Health {
+        status: "ok".to_string(),
+    })
+}
+
+async fn ready<C: HttpClient + Send + Sync + 'static>(
+    State(middle_ware): State<Arc<CheckPointMiddleware<C>>>,
+) -> Readiness {
+    middle_ware.readiness()
+}
+
+fn to_api_response(
+    displayable_result: DisplayableResult,
+    verbose: bool,
+    cache: Option<CacheInfo>,
+) -> ApiResponse {
+    let verdict = displayable_result.verdict();
+    let payload = if verbose {
         Some(displayable_result)
     } else {
         None
     };
-
-    let api_response = ApiResponse {
-        block_root,
-        epoch,
+    ApiResponse {
+        verdict,
+        cache,
         payload,
-    };
-
-    Ok(Json(api_response))
+    }
 }
@@ -1,20 +1,35 @@
 use crate::errors::AppError;
-use crate::processor::{process_to_displayable_format, DisplayableResult};
+use crate::metrics::Metrics;
+use crate::processor::{
+    is_quorum_decided, operator_weight, process_to_displayable_format, verify_headers,
+    CheckpointKind, DisplayableResult,
+};
+use crate::quorum::QuorumPolicy;
 use async_trait::async_trait;
 use futures::future::join_all;
+use futures::stream::{FuturesUnordered, StreamExt};
 
 use reqwest::Response;
 
-use crate::args::Network;
+use crate::network::{builtin_networks, Network};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::{Debug, Formatter};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use strum_macros::Display;
+use tokio::time::{sleep, timeout, timeout_at, Instant};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ResponsePayloadWithEndpointInfo {
     pub payload: Result<SuccessEndpointPayload, AppError>,
     pub endpoint: String,
+    pub weight: u64,
+    pub operator: String,
+    pub sync_issues: Vec<SyncIssue>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -35,105 +50,1048 @@ pub struct Data {
     pub previous_justified: BlockInfo,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GenesisPayload {
+    pub data: Genesis,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Genesis {
+    pub genesis_time: String,
+    pub genesis_validators_root: String,
+    pub genesis_fork_version: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ForkPayload {
+    pub data: Fork,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Fork {
+    pub previous_version: String,
+    pub current_version: String,
+    pub epoch: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HeaderPayload {
+    pub data: HeaderData,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HeaderData {
+    pub root: String,
+    pub header: SignedBeaconBlockHeader,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StateRootPayload {
+    pub data: StateRoot,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StateRoot {
+    pub root: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignedBeaconBlockHeader {
+    pub message: BeaconBlockHeader,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BeaconBlockHeader {
+    pub slot: String,
+    pub proposer_index: String,
+    pub parent_root: String,
+    pub state_root: String,
+    pub body_root: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SyncingPayload {
+    pub data: SyncStatus,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SyncStatus {
+    pub head_slot: String,
+    pub sync_distance: String,
+    pub is_syncing: bool,
+    #[serde(default)]
+    pub is_optimistic: bool,
+    #[serde(default)]
+    pub el_offline: bool,
+}
+
+impl SyncStatus {
+    pub fn issues(&self) -> Vec<SyncIssue> {
+        [
+            (self.is_syncing, SyncIssue::Syncing),
+            (self.is_optimistic, SyncIssue::Optimistic),
+            (self.el_offline, SyncIssue::ElOffline),
+        ]
+        .into_iter()
+        .filter(|(present, _)| *present)
+        .map(|(_, issue)| issue)
+        .collect()
+    }
+}
+
+// Why a provider may report a stale checkpoint
+#[derive(Display, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SyncIssue {
+    Syncing,
+    Optimistic,
+    ElOffline,
+}
+
+// What to do with providers that are syncing, optimistic or have their execution layer offline,
+// according to /eth/v1/node/syncing
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SyncCheck {
+    // the sync status is not requested
+    #[default]
+    Off,
+    // their vote is counted, and they are listed with the issues found
+    Flag,
+    // they are reported as failures instead of voting
+    Drop,
+}
+
+impl fmt::Display for SyncCheck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncCheck::Off => write!(f, "off"),
+            SyncCheck::Flag => write!(f, "flag"),
+            SyncCheck::Drop => write!(f, "drop"),
+        }
+    }
+}
+
+impl FromStr for SyncCheck {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "off" => Ok(SyncCheck::Off),
+            "flag" => Ok(SyncCheck::Flag),
+            "drop" => Ok(SyncCheck::Drop),
+            other => Err(AppError::InvalidSyncCheck {
+                message: format!("Unknown sync check {other}. Expected one of off, flag or drop"),
+            }),
+        }
+    }
+}
+
+impl TryFrom<String> for SyncCheck {
+    type Error = AppError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<SyncCheck> for String {
+    fn from(value: SyncCheck) -> Self {
+        value.to_string()
+    }
+}
+
+// The chain a provider is on, as reported by it during the epoch it was fetched in
+#[derive(Debug, Clone)]
+struct ProviderNetwork {
+    genesis_validators_root: String,
+    current_fork_version: String,
+    epoch: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct CheckpointClient<C: HttpClient> {
     client: C,
     endpoints_config: EndpointsConfig,
     state_id: StateId,
+    quorum_policy: QuorumPolicy,
+    request_config: RequestConfig,
+    early_exit: bool,
+    sync_check: SyncCheck,
+    network_check: bool,
+    provider_networks: Arc<Mutex<HashMap<String, ProviderNetwork>>>,
+    metrics: Arc<Metrics>,
 }
 
-#[derive(Debug, Clone)]
+// The state the finality checkpoints are read from: finalized, head, justified, genesis, a slot
+// number or a 0x prefixed state root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum StateId {
+    #[default]
     Finalized,
-    Slot(u128), // TODO is u128 to big?
+    Head,
+    Justified,
+    Genesis,
+    Slot(u64),
+    Root(String),
+}
+
+impl StateId {
+    // Finalized, justified and head move with the chain, while the other ids always name the
+    // same state
+    pub fn follows_chain(&self) -> bool {
+        matches!(
+            self,
+            StateId::Finalized | StateId::Justified | StateId::Head
+        )
+    }
 }
 
 impl fmt::Display for StateId {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             StateId::Finalized => write!(f, "finalized"),
-            StateId::Slot(slot) => write!(f, "{:?}", slot.to_string()),
+            StateId::Head => write!(f, "head"),
+            StateId::Justified => write!(f, "justified"),
+            StateId::Genesis => write!(f, "genesis"),
+            StateId::Slot(slot) => write!(f, "{slot}"),
+            StateId::Root(root) => write!(f, "{root}"),
+        }
+    }
+}
+
+impl FromStr for StateId {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        match s.as_str() {
+            "finalized" => Ok(StateId::Finalized),
+            "head" => Ok(StateId::Head),
+            "justified" => Ok(StateId::Justified),
+            "genesis" => Ok(StateId::Genesis),
+            root if root.starts_with("0x") => {
+                let hex = &root[2..];
+                if hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    Ok(StateId::Root(root.to_string()))
+                } else {
+                    Err(AppError::InvalidStateId {
+                        message: format!("State root {root} must be 32 bytes hex encoded"),
+                    })
+                }
+            }
+            slot => slot.parse::<u64>().map(StateId::Slot).map_err(|_| {
+                AppError::InvalidStateId {
+                    message: format!(
+                        "Unknown state id {slot}. Expected one of finalized, head, justified, genesis, a slot number or a 0x prefixed state root"
+                    ),
+                }
+            }),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl TryFrom<String> for StateId {
+    type Error = AppError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<StateId> for String {
+    fn from(value: StateId) -> Self {
+        value.to_string()
+    }
+}
+
+// Parameters of a single quorum round that can be overridden per request
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FetchOptions {
+    pub state_id: StateId,
+    pub quorum_policy: QuorumPolicy,
+    // stop waiting for endpoints once the outcome can no longer change
+    pub early_exit: bool,
+    pub sync_check: SyncCheck,
+}
+
+// An endpoint is listed in the config either as a plain url, or as a map with the url, the
+// weight its vote carries when establishing quorum and the operator running it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "EndpointEntry")]
+pub struct Endpoint {
+    pub url: String,
+    pub weight: u64,
+    pub operator: Option<String>,
+}
+
+impl Endpoint {
+    // endpoints without an operator label are treated as run by their own operator
+    pub fn operator(&self) -> &str {
+        self.operator.as_deref().unwrap_or(&self.url)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EndpointEntry {
+    Url(String),
+    Detailed {
+        url: String,
+        #[serde(default = "default_weight")]
+        weight: u64,
+        operator: Option<String>,
+    },
+}
+
+fn default_weight() -> u64 {
+    1
+}
+
+impl From<EndpointEntry> for Endpoint {
+    fn from(entry: EndpointEntry) -> Self {
+        match entry {
+            EndpointEntry::Url(url) => Endpoint::from(url),
+            EndpointEntry::Detailed {
+                url,
+                weight,
+                operator,
+            } => Endpoint {
+                url,
+                weight,
+                operator,
+            },
+        }
+    }
+}
+
+impl From<String> for Endpoint {
+    fn from(url: String) -> Self {
+        Endpoint {
+            url,
+            weight: default_weight(),
+            operator: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct EndpointsConfig {
-    pub endpoints: HashMap<String, Vec<String>>,
+    pub endpoints: HashMap<String, Vec<Endpoint>>,
+    #[serde(default)]
+    pub quorum: Option<QuorumPolicy>,
+    #[serde(default)]
+    pub min_distinct_operators: Option<u64>,
+    #[serde(default)]
+    pub networks: HashMap<String, Network>,
+}
+
+impl EndpointsConfig {
+    // the built-in networks, overridden and extended by the ones in the config file
+    pub fn all_networks(&self) -> HashMap<String, Network> {
+        let mut networks = builtin_networks();
+        networks.extend(self.networks.clone());
+        networks
+    }
+
+    pub fn network(&self, name: &str) -> Result<Network, AppError> {
+        self.all_networks()
+            .remove(&name.to_lowercase())
+            .ok_or(AppError::UnknownNetwork {
+                message: format!(
+                    "Network {name} is not a built-in network and is not defined in the networks section of the config"
+                ),
+            })
+    }
+}
+
+// Timeouts and retries applied to the requests made to the endpoints. The request timeout and
+// retries apply per endpoint, while the deadline bounds the whole quorum round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestConfig {
+    pub request_timeout: Duration,
+    pub deadline: Duration,
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+}
+
+impl Default for RequestConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(10),
+            deadline: Duration::from_secs(30),
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(250),
+        }
+    }
+}
+
+// Retries past this many would only wait out the deadline, the backoff doubling every time
+pub const MAX_RETRIES: u32 = 10;
+
+impl RequestConfig {
+    // the wait before the retry following the given attempt, which never exceeds the deadline
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        2u32.checked_pow(attempt)
+            .and_then(|factor| self.initial_backoff.checked_mul(factor))
+            .map_or(self.deadline, |backoff| backoff.min(self.deadline))
+    }
 }
 
 #[async_trait]
-pub trait HttpClient {
+pub trait HttpClient: Sync {
     async fn send_request(&self, path: String) -> Result<Response, AppError>;
+
+    // requests the SSZ encoding of the resource instead of json
+    async fn send_ssz_request(&self, path: String) -> Result<Response, AppError> {
+        self.send_request(path).await
+    }
+}
+
+fn to_request_error(path: &str, e: reqwest::Error) -> AppError {
+    let endpoint = path.to_string();
+    let message = e.to_string();
+    if e.is_timeout() {
+        AppError::Timeout { endpoint, message }
+    } else if e.is_connect() {
+        AppError::Connect { endpoint, message }
+    } else {
+        AppError::Request { endpoint, message }
+    }
 }
 
 #[async_trait]
 impl HttpClient for reqwest::Client {
     async fn send_request(&self, path: String) -> Result<Response, AppError> {
-        self.get(path)
+        self.get(&path)
             .send()
             .await
-            .map_err(|e| AppError::EndpointResponseError(e.to_string()))
+            .map_err(|e| to_request_error(&path, e))
+    }
+
+    async fn send_ssz_request(&self, path: String) -> Result<Response, AppError> {
+        self.get(&path)
+            .header(reqwest::header::ACCEPT, "application/octet-stream")
+            .send()
+            .await
+            .map_err(|e| to_request_error(&path, e))
     }
 }
 
+// An SSZ encoded response, with the fork it was encoded for when the provider reported it
+#[derive(Debug, Clone)]
+pub struct SszResponse {
+    pub bytes: Vec<u8>,
+    pub fork: Option<String>,
+}
+
 impl<C: HttpClient> CheckpointClient<C> {
     pub fn new(client: C, state_id: StateId, endpoints: EndpointsConfig) -> Self {
         Self {
             client,
+            quorum_policy: endpoints.quorum.unwrap_or_default(),
             endpoints_config: endpoints,
             state_id,
+            request_config: RequestConfig::default(),
+            early_exit: false,
+            sync_check: SyncCheck::default(),
+            network_check: true,
+            provider_networks: Arc::new(Mutex::new(HashMap::new())),
+            metrics: Arc::new(Metrics::new()),
+        }
+    }
+
+    pub fn with_sync_check(mut self, sync_check: SyncCheck) -> Self {
+        self.sync_check = sync_check;
+        self
+    }
+
+    // whether to check that providers are on the requested network before counting their vote
+    pub fn with_network_check(mut self, network_check: bool) -> Self {
+        self.network_check = network_check;
+        self
+    }
+
+    pub fn with_early_exit(mut self, early_exit: bool) -> Self {
+        self.early_exit = early_exit;
+        self
+    }
+
+    pub fn with_request_config(mut self, request_config: RequestConfig) -> Self {
+        self.request_config = request_config;
+        self
+    }
+
+    pub fn with_quorum_policy(mut self, quorum_policy: QuorumPolicy) -> Self {
+        self.quorum_policy = quorum_policy;
+        self
+    }
+
+    // the options used when none are given for a request
+    pub fn default_options(&self) -> FetchOptions {
+        FetchOptions {
+            state_id: self.state_id.clone(),
+            quorum_policy: self.quorum_policy,
+            early_exit: self.early_exit,
+            sync_check: self.sync_check,
         }
     }
+
     pub async fn fetch_finality_checkpoints(
         &self,
-        network: Network,
+        network: &str,
     ) -> Result<DisplayableResult, AppError> {
-        let endpoints_config = &self.endpoints_config;
-        let endpoints: &Vec<String> = endpoints_config.endpoints.get(&network.to_string().to_lowercase()).ok_or(
-            AppError::EndpointsNotFound(format!(r#"Endpoint not found for {network} network. Ensure it is present in the config file and the network name is specified in lowercase."#)),
-        )?;
+        self.fetch_finality_checkpoints_with_options(network, &self.default_options())
+            .await
+    }
+
+    pub async fn fetch_finality_checkpoints_with_options(
+        &self,
+        network: &str,
+        options: &FetchOptions,
+    ) -> Result<DisplayableResult, AppError> {
+        let network_config = self.endpoints_config.network(network)?;
+        // the deadline bounds the whole round, the header verification included
+        let deadline = Instant::now() + self.request_config.deadline;
+        let results = self
+            .fetch_responses(
+                network,
+                &network_config,
+                options,
+                deadline,
+                |results, endpoints| {
+                    options.early_exit
+                        && self.is_decided(results, endpoints, CheckpointKind::Finalized, options)
+                },
+            )
+            .await?;
+        let mut result = process_to_displayable_format(
+            results,
+            CheckpointKind::Finalized,
+            options.quorum_policy,
+            self.min_distinct_operators(),
+        );
+        self.verify_header(&network_config, &mut result, deadline)
+            .await;
+        self.metrics.observe_failures(network, &result);
+        if *options == self.default_options() {
+            self.metrics.observe_result(network, &result);
+        }
+        Ok(result)
+    }
 
-        let results = join_all(endpoints.iter().map(|endpoint| async {
-            let raw_response = async {
-                let path = format!(
-                    "{}/eth/v1/beacon/states/{}/finality_checkpoints",
-                    endpoint.clone(),
-                    self.state_id
-                );
-                let result = self.client.send_request(path);
-                match result.await {
-                    Ok(res) => {
-                        if res.status().is_success() {
-                            ResponsePayloadWithEndpointInfo {
-                                payload: res
-                                    .json::<SuccessEndpointPayload>()
-                                    .await
-                                    .map_err(|e| AppError::EndpointResponseError(e.to_string())),
-                                endpoint: endpoint.clone(),
-                            }
-                        } else {
-                            ResponsePayloadWithEndpointInfo {
-                                payload: Err(AppError::EndpointResponseError(format!(
-                                    "Error with calling {} status code {}",
-                                    endpoint.clone(),
-                                    res.status().to_string()
-                                ))),
-                                endpoint: endpoint.clone(),
-                            }
-                        }
-                    }
-                    Err(e) => ResponsePayloadWithEndpointInfo {
-                        payload: Err(e),
-                        endpoint: endpoint.clone(),
-                    },
+    // runs the quorum analysis independently for the finalized, current justified and
+    // previous justified checkpoints, using a single request per endpoint
+    pub async fn fetch_all_checkpoints(
+        &self,
+        network: &str,
+    ) -> Result<Vec<DisplayableResult>, AppError> {
+        self.fetch_all_checkpoints_with_options(network, &self.default_options())
+            .await
+    }
+
+    pub async fn fetch_all_checkpoints_with_options(
+        &self,
+        network: &str,
+        options: &FetchOptions,
+    ) -> Result<Vec<DisplayableResult>, AppError> {
+        let network_config = self.endpoints_config.network(network)?;
+        // the deadline bounds the whole round, the header verification included
+        let deadline = Instant::now() + self.request_config.deadline;
+        let results = self
+            .fetch_responses(
+                network,
+                &network_config,
+                options,
+                deadline,
+                |results, endpoints| {
+                    options.early_exit
+                        && CheckpointKind::ALL.iter().all(|checkpoint| {
+                            self.is_decided(results, endpoints, *checkpoint, options)
+                        })
+                },
+            )
+            .await?;
+        let mut all_results: Vec<DisplayableResult> = CheckpointKind::ALL
+            .iter()
+            .map(|checkpoint| {
+                process_to_displayable_format(
+                    results.clone(),
+                    *checkpoint,
+                    options.quorum_policy,
+                    self.min_distinct_operators(),
+                )
+            })
+            .collect();
+        for result in all_results.iter_mut() {
+            if result.checkpoint == CheckpointKind::Finalized {
+                self.verify_header(&network_config, result, deadline).await;
+                self.metrics.observe_failures(network, result);
+                if *options == self.default_options() {
+                    self.metrics.observe_result(network, result);
                 }
             }
-            .await;
-            raw_response
+        }
+        Ok(all_results)
+    }
+
+    // requests the header of the block that reached quorum from the providers that agreed on it,
+    // and checks that they all return the same one, within the epoch of the checkpoint, by the
+    // deadline of the round
+    async fn verify_header(
+        &self,
+        network: &Network,
+        result: &mut DisplayableResult,
+        deadline: Instant,
+    ) {
+        let Some(block_info) = result.canonical_checkpoint().cloned() else {
+            return;
+        };
+        let endpoints: Vec<String> = result
+            .canonical
+            .iter()
+            .flat_map(|canonical| canonical.values().flatten())
+            .map(|value| value.endpoint.clone())
+            .collect();
+        let headers = join_all(endpoints.into_iter().map(|url| {
+            let path = format!("{url}/eth/v1/beacon/headers/{}", block_info.root);
+            let endpoint = Endpoint::from(url);
+            async move {
+                let header = timeout_at(deadline, self.request_with_retries(&endpoint, path))
+                    .await
+                    .unwrap_or_else(|_| Err(self.deadline_exceeded(&endpoint)));
+                (endpoint.url, header)
+            }
+        }))
+        .await;
+        result.header = Some(verify_headers(
+            &block_info,
+            network.slots_per_epoch,
+            headers,
+        ));
+    }
+
+    // requests the root of the state at the slot from the providers that agreed on the canonical
+    // checkpoint of the result, and returns it once providers with the weight and the distinct
+    // operators the checkpoint needed agree on it, an operator voting once
+    pub async fn fetch_state_root(
+        &self,
+        result: &DisplayableResult,
+        slot: u64,
+    ) -> Result<String, AppError> {
+        let deadline = Instant::now() + self.request_config.deadline;
+        let voters: Vec<(String, String, u64)> = result
+            .canonical
+            .iter()
+            .flat_map(|canonical| canonical.values().flatten())
+            .map(|value| (value.endpoint.clone(), value.operator.clone(), value.weight))
+            .collect();
+        let roots = join_all(voters.into_iter().map(|(url, operator, weight)| {
+            let path = format!("{url}/eth/v1/beacon/states/{slot}/root");
+            let endpoint = Endpoint::from(url);
+            async move {
+                let root: Result<StateRootPayload, AppError> =
+                    timeout_at(deadline, self.request_with_retries(&endpoint, path))
+                        .await
+                        .unwrap_or_else(|_| Err(self.deadline_exceeded(&endpoint)));
+                root.ok()
+                    .map(|root| (root.data.root.to_lowercase(), operator, weight))
+            }
         }))
         .await;
+        let mut tally: HashMap<String, Vec<(String, u64)>> = HashMap::new();
+        for (root, operator, weight) in roots.into_iter().flatten() {
+            tally.entry(root).or_default().push((operator, weight));
+        }
+        if tally.len() > 1 {
+            return Err(AppError::NoVerifiedCheckpoint {
+                message: format!(
+                    "Providers returned different state roots at slot {slot}: {}",
+                    tally.keys().cloned().collect::<Vec<String>>().join(", ")
+                ),
+            });
+        }
+        let (root, votes) = tally.into_iter().next().unwrap_or_default();
+        let weight = operator_weight(
+            votes
+                .iter()
+                .map(|(operator, weight)| (operator.as_str(), *weight)),
+        );
+        let operators = votes
+            .iter()
+            .map(|(operator, _)| operator.as_str())
+            .collect::<HashSet<&str>>()
+            .len() as u64;
+        if votes.is_empty() || weight < result.threshold {
+            return Err(AppError::NoVerifiedCheckpoint {
+                message: format!(
+                    "Only a weight of {weight} out of the {} needed agrees on the state root at slot {slot}",
+                    result.threshold
+                ),
+            });
+        }
+        if operators < result.min_distinct_operators {
+            return Err(AppError::NoVerifiedCheckpoint {
+                message: format!(
+                    "Only {operators} out of the {} distinct operators needed agree on the state root at slot {slot}",
+                    result.min_distinct_operators
+                ),
+            });
+        }
+        Ok(root)
+    }
+
+    fn is_decided(
+        &self,
+        results: &[Option<ResponsePayloadWithEndpointInfo>],
+        endpoints: &[Endpoint],
+        checkpoint: CheckpointKind,
+        options: &FetchOptions,
+    ) -> bool {
+        let received: Vec<&ResponsePayloadWithEndpointInfo> = results.iter().flatten().collect();
+        let pending: Vec<(&str, u64)> = results
+            .iter()
+            .zip(endpoints)
+            .filter(|(result, _)| result.is_none())
+            .map(|(_, endpoint)| (endpoint.operator(), endpoint.weight))
+            .collect();
+        is_quorum_decided(
+            &received,
+            &pending,
+            checkpoint,
+            options.quorum_policy,
+            self.min_distinct_operators(),
+        )
+    }
+
+    fn min_distinct_operators(&self) -> u64 {
+        self.endpoints_config.min_distinct_operators.unwrap_or(1)
+    }
+
+    // requests the finality checkpoints from every endpoint of the network, returning as soon as
+    // is_decided is true for the results received so far
+    async fn fetch_responses(
+        &self,
+        network: &str,
+        network_config: &Network,
+        options: &FetchOptions,
+        deadline: Instant,
+        is_decided: impl Fn(&[Option<ResponsePayloadWithEndpointInfo>], &[Endpoint]) -> bool,
+    ) -> Result<Vec<ResponsePayloadWithEndpointInfo>, AppError> {
+        let endpoints_config = &self.endpoints_config;
+        let endpoints: &Vec<Endpoint> = endpoints_config.endpoints.get(&network.to_lowercase()).ok_or(
+            AppError::EndpointsNotFound { message: format!(r#"Endpoint not found for {network} network. Ensure it is present in the config file and the network name is specified in lowercase."#) },
+        )?;
+        let expected_network = self.network_check.then_some(network_config);
+
+        let mut pending: FuturesUnordered<_> = endpoints
+            .iter()
+            .enumerate()
+            .map(|(index, endpoint)| async move {
+                (
+                    index,
+                    self.fetch_endpoint(endpoint, expected_network, options, deadline)
+                        .await,
+                )
+            })
+            .collect();
+
+        let mut results: Vec<Option<ResponsePayloadWithEndpointInfo>> = vec![None; endpoints.len()];
+        while let Some((index, result)) = pending.next().await {
+            results[index] = Some(result);
+            if is_decided(&results, endpoints) {
+                break;
+            }
+        }
+        // dropping the futures cancels the requests that are still outstanding
+        drop(pending);
+
+        Ok(results
+            .into_iter()
+            .zip(endpoints)
+            .map(|(result, endpoint)| {
+                result.unwrap_or_else(|| ResponsePayloadWithEndpointInfo {
+                    payload: Err(AppError::NotAwaited {
+                        endpoint: endpoint.url.clone(),
+                    }),
+                    endpoint: endpoint.url.clone(),
+                    weight: endpoint.weight,
+                    operator: endpoint.operator().to_string(),
+                    sync_issues: vec![],
+                })
+            })
+            .collect())
+    }
+
+    async fn fetch_endpoint(
+        &self,
+        endpoint: &Endpoint,
+        expected_network: Option<&Network>,
+        options: &FetchOptions,
+        deadline: Instant,
+    ) -> ResponsePayloadWithEndpointInfo {
+        let path = format!(
+            "{}/eth/v1/beacon/states/{}/finality_checkpoints",
+            endpoint.url, options.state_id
+        );
+        let mut sync_issues = vec![];
+        let request = async {
+            if let Some(network) = expected_network {
+                self.check_network(endpoint, network).await?;
+            }
+            if options.sync_check != SyncCheck::Off {
+                let syncing: SyncingPayload = self
+                    .request_with_retries(endpoint, format!("{}/eth/v1/node/syncing", endpoint.url))
+                    .await?;
+                sync_issues = syncing.data.issues();
+                if options.sync_check == SyncCheck::Drop && !sync_issues.is_empty() {
+                    return Err(AppError::NotSynced {
+                        endpoint: endpoint.url.clone(),
+                        issues: sync_issues.clone(),
+                    });
+                }
+            }
+            self.request_with_retries(endpoint, path).await
+        };
+        // endpoints that have not responded by the deadline are recorded as timed out
+        let payload = timeout_at(deadline, request)
+            .await
+            .unwrap_or_else(|_| Err(self.deadline_exceeded(endpoint)));
+        ResponsePayloadWithEndpointInfo {
+            payload,
+            endpoint: endpoint.url.clone(),
+            weight: endpoint.weight,
+            operator: endpoint.operator().to_string(),
+            sync_issues,
+        }
+    }
+
+    fn deadline_exceeded(&self, endpoint: &Endpoint) -> AppError {
+        AppError::Timeout {
+            endpoint: endpoint.url.clone(),
+            message: format!(
+                "No response within the deadline of {:?}",
+                self.request_config.deadline
+            ),
+        }
+    }
+
+    // rejects providers whose genesis validators root or current fork version are not the ones of
+    // the network. What a provider reports is cached for the rest of the epoch.
+    async fn check_network(&self, endpoint: &Endpoint, network: &Network) -> Result<(), AppError> {
+        let epoch = network.current_epoch();
+        let cached = self
+            .provider_networks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&endpoint.url)
+            .filter(|provider| provider.epoch == epoch)
+            .cloned();
+        let provider = match cached {
+            Some(provider) => provider,
+            None => {
+                let genesis: GenesisPayload = self
+                    .request_with_retries(
+                        endpoint,
+                        format!("{}/eth/v1/beacon/genesis", endpoint.url),
+                    )
+                    .await?;
+                let fork: ForkPayload = self
+                    .request_with_retries(
+                        endpoint,
+                        format!("{}/eth/v1/beacon/states/head/fork", endpoint.url),
+                    )
+                    .await?;
+                let provider = ProviderNetwork {
+                    genesis_validators_root: genesis.data.genesis_validators_root,
+                    current_fork_version: fork.data.current_version,
+                    epoch,
+                };
+                self.provider_networks
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(endpoint.url.clone(), provider.clone());
+                provider
+            }
+        };
+
+        if !provider
+            .genesis_validators_root
+            .eq_ignore_ascii_case(&network.genesis_validators_root)
+        {
+            return Err(AppError::NetworkMismatch {
+                endpoint: endpoint.url.clone(),
+                message: format!(
+                    "genesis validators root {} does not match {}",
+                    provider.genesis_validators_root, network.genesis_validators_root
+                ),
+            });
+        }
+        if !network.is_current_fork_version(&provider.current_fork_version, epoch) {
+            return Err(AppError::NetworkMismatch {
+                endpoint: endpoint.url.clone(),
+                message: format!(
+                    "current fork version {} is not the one of epoch {epoch}, {}",
+                    provider.current_fork_version,
+                    network.fork_versions_at(epoch).join(" or ")
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn network(&self, name: &str) -> Result<Network, AppError> {
+        self.endpoints_config.network(name)
+    }
+
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    // the networks endpoints are configured for
+    pub fn networks(&self) -> Vec<String> {
+        self.endpoints_config.endpoints.keys().cloned().collect()
+    }
+
+    // requests the SSZ encoding of a resource from a provider, with the given timeout per attempt
+    pub async fn fetch_ssz(
+        &self,
+        url: &str,
+        path: String,
+        request_timeout: Duration,
+    ) -> Result<SszResponse, AppError> {
+        self.with_retries(|| async {
+            let started = Instant::now();
+            let attempt = async {
+                let res = self
+                    .client
+                    .send_ssz_request(path.clone())
+                    .await
+                    .map_err(|error| error.with_endpoint(url))?;
+                read_ssz(url, res).await
+            };
+            let result = timeout(request_timeout, attempt).await.unwrap_or_else(|_| {
+                Err(AppError::Timeout {
+                    endpoint: url.to_string(),
+                    message: format!("Request timed out after {request_timeout:?}"),
+                })
+            });
+            self.metrics.observe_request(url, &path, started.elapsed());
+            result
+        })
+        .await
+    }
+
+    async fn request_with_retries<T: DeserializeOwned>(
+        &self,
+        endpoint: &Endpoint,
+        path: String,
+    ) -> Result<T, AppError> {
+        self.with_retries(|| self.request_once(endpoint, path.clone()))
+            .await
+    }
+
+    // retries transient errors with an exponential backoff
+    async fn with_retries<T, F, Fut>(&self, request: F) -> Result<T, AppError>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<T, AppError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match request().await {
+                Ok(payload) => return Ok(payload),
+                Err(error) if error.is_transient() && attempt < self.request_config.max_retries => {
+                    sleep(self.request_config.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    async fn request_once<T: DeserializeOwned>(
+        &self,
+        endpoint: &Endpoint,
+        path: String,
+    ) -> Result<T, AppError> {
+        let request_timeout = self.request_config.request_timeout;
+        let started = Instant::now();
+        let attempt = async {
+            let res = self
+                .client
+                .send_request(path.clone())
+                .await
+                .map_err(|error| error.with_endpoint(&endpoint.url))?;
+            read_json(&endpoint.url, res).await
+        };
+        let result = match timeout(request_timeout, attempt).await {
+            Ok(result) => result,
+            Err(_) => Err(AppError::Timeout {
+                endpoint: endpoint.url.clone(),
+                message: format!("Request timed out after {request_timeout:?}"),
+            }),
+        };
+        self.metrics
+            .observe_request(&endpoint.url, &path, started.elapsed());
+        result
+    }
+}
+
+const BODY_SNIPPET_LENGTH: usize = 200;
+
+async fn read_ssz(endpoint: &str, res: Response) -> Result<SszResponse, AppError> {
+    let status = res.status();
+    let fork = res
+        .headers()
+        .get("Eth-Consensus-Version")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let bytes = res.bytes().await.map_err(|e| AppError::Decode {
+        endpoint: endpoint.to_string(),
+        message: e.to_string(),
+    })?;
+    if !status.is_success() {
+        return Err(AppError::HttpStatus {
+            endpoint: endpoint.to_string(),
+            code: status.as_u16(),
+            body: String::from_utf8_lossy(&bytes)
+                .chars()
+                .take(BODY_SNIPPET_LENGTH)
+                .collect(),
+        });
+    }
+    Ok(SszResponse {
+        bytes: bytes.to_vec(),
+        fork,
+    })
+}
 
-        Ok(process_to_displayable_format(results))
+// Reads the json payload of a successful response. Non 2xx responses are reported with their
+// status code and the beginning of their body, bodies that are not json as decode errors and
+// json that does not match the expected payload as an invalid payload.
+async fn read_json<T: DeserializeOwned>(endpoint: &str, res: Response) -> Result<T, AppError> {
+    let status = res.status();
+    let body = res.text().await.map_err(|e| AppError::Decode {
+        endpoint: endpoint.to_string(),
+        message: e.to_string(),
+    })?;
+    if !status.is_success() {
+        return Err(AppError::HttpStatus {
+            endpoint: endpoint.to_string(),
+            code: status.as_u16(),
+            body: body.chars().take(BODY_SNIPPET_LENGTH).collect(),
+        });
     }
+    let value: serde_json::Value = serde_json::from_str(&body).map_err(|e| AppError::Decode {
+        endpoint: endpoint.to_string(),
+        message: e.to_string(),
+    })?;
+    serde_json::from_value(value).map_err(|e| AppError::InvalidPayload {
+        endpoint: endpoint.to_string(),
+        message: e.to_string(),
+    })
 }
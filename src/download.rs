@@ -0,0 +1,183 @@
+use crate::client::{BeaconBlockHeader, BlockInfo, CheckpointClient, HttpClient};
+use crate::errors::AppError;
+use crate::forks::ForkName;
+use crate::network::Network;
+use crate::processor::{DisplayableResult, FailurePayload};
+use crate::ssz::Root;
+use colored::Colorize;
+use std::path::Path;
+use std::time::Duration;
+
+// A finalized state and block downloaded from a provider, whose roots match the checkpoint
+#[derive(Debug, Clone)]
+pub struct VerifiedCheckpoint {
+    pub endpoint: String,
+    pub fork: ForkName,
+    pub slot: String,
+    pub block_root: String,
+    pub state_root: String,
+    pub state: Vec<u8>,
+    pub block: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct DownloadOutcome {
+    pub checkpoint: Option<VerifiedCheckpoint>,
+    // the providers tried before one returned a state and block matching the checkpoint
+    pub failure: Vec<FailurePayload>,
+}
+
+pub fn to_hex(root: &Root) -> String {
+    format!("0x{}", hex::encode(root))
+}
+
+// The canonical checkpoint of the result along with its verified block header
+pub fn verified_checkpoint(
+    result: &DisplayableResult,
+) -> Result<(&BlockInfo, &BeaconBlockHeader), AppError> {
+    match (result.canonical_checkpoint(), result.verified_header()) {
+        (Some(block_info), Some(header)) => Ok((block_info, header)),
+        _ => Err(AppError::NoVerifiedCheckpoint {
+            message: format!(
+                "No checkpoint with a verified block header, quorum status is {:?}",
+                result.status()
+            ),
+        }),
+    }
+}
+
+// Downloads the state and block of the canonical checkpoint of the result from the providers that
+// agreed on it, one after the other, until one returns a state and block whose hash tree roots
+// match the state root and block root of the checkpoint.
+pub async fn download_checkpoint<C: HttpClient>(
+    checkpoint_client: &CheckpointClient<C>,
+    network: &str,
+    result: &DisplayableResult,
+    request_timeout: Duration,
+) -> Result<DownloadOutcome, AppError> {
+    let (block_info, header) = verified_checkpoint(result)?;
+    let network_config = checkpoint_client.network(network)?;
+    let endpoints: Vec<&String> = result
+        .canonical
+        .iter()
+        .flat_map(|canonical| canonical.values().flatten())
+        .map(|value| &value.endpoint)
+        .collect();
+
+    let mut failure = vec![];
+    for endpoint in endpoints {
+        match download_from(
+            checkpoint_client,
+            &network_config,
+            endpoint,
+            &header.slot,
+            &block_info.root,
+            &header.state_root,
+            request_timeout,
+        )
+        .await
+        {
+            Ok(checkpoint) => {
+                return Ok(DownloadOutcome {
+                    checkpoint: Some(checkpoint),
+                    failure,
+                })
+            }
+            Err(error) => failure.push(FailurePayload {
+                payload: error,
+                endpoint: endpoint.clone(),
+            }),
+        }
+    }
+    Ok(DownloadOutcome {
+        checkpoint: None,
+        failure,
+    })
+}
+
+async fn download_from<C: HttpClient>(
+    checkpoint_client: &CheckpointClient<C>,
+    network: &Network,
+    endpoint: &str,
+    slot: &str,
+    block_root: &str,
+    state_root: &str,
+    request_timeout: Duration,
+) -> Result<VerifiedCheckpoint, AppError> {
+    let state = checkpoint_client
+        .fetch_ssz(
+            endpoint,
+            format!("{endpoint}/eth/v2/debug/beacon/states/{slot}"),
+            request_timeout,
+        )
+        .await?;
+    // the fork is taken from the state itself when the fork versions of the network are known
+    let fork = match (&state.fork, network.fork_versions.is_empty()) {
+        (Some(fork), true) => fork.parse()?,
+        _ => ForkName::of_state(network, &state.bytes)?,
+    };
+    let block = checkpoint_client
+        .fetch_ssz(
+            endpoint,
+            format!("{endpoint}/eth/v2/beacon/blocks/{block_root}"),
+            request_timeout,
+        )
+        .await?;
+
+    // hashing a state takes a while, so it is done off the async runtime
+    let (state, block, state_hash, block_hash) = tokio::task::spawn_blocking(move || {
+        let state_hash = fork.beacon_state().hash_tree_root(&state.bytes);
+        let block_hash = fork
+            .signed_beacon_block()
+            .field(&block.bytes, "message")
+            .and_then(|message| fork.beacon_block().hash_tree_root(message));
+        (state.bytes, block.bytes, state_hash, block_hash)
+    })
+    .await
+    .map_err(|e| AppError::InvalidSsz {
+        message: e.to_string(),
+    })?;
+    let (state_hash, block_hash) = (to_hex(&state_hash?), to_hex(&block_hash?));
+
+    if !state_hash.eq_ignore_ascii_case(state_root) {
+        return Err(AppError::RootMismatch {
+            endpoint: endpoint.to_string(),
+            message: format!("state hashes to {state_hash} instead of {state_root}"),
+        });
+    }
+    if !block_hash.eq_ignore_ascii_case(block_root) {
+        return Err(AppError::RootMismatch {
+            endpoint: endpoint.to_string(),
+            message: format!("block hashes to {block_hash} instead of {block_root}"),
+        });
+    }
+    Ok(VerifiedCheckpoint {
+        endpoint: endpoint.to_string(),
+        fork,
+        slot: slot.to_string(),
+        block_root: block_root.to_string(),
+        state_root: state_root.to_string(),
+        state,
+        block,
+    })
+}
+
+pub fn print_download_outcome(outcome: &DownloadOutcome, state_file: &Path, block_file: &Path) {
+    if let Some(checkpoint) = &outcome.checkpoint {
+        println!("{}", "Downloaded:".blue().bold());
+        println!("\t Endpoint: {}", checkpoint.endpoint.green());
+        println!("\t Fork: {}", checkpoint.fork.to_string().green());
+        println!("\t Slot: {}", checkpoint.slot.green());
+        println!("\t Block root: {}", checkpoint.block_root.green());
+        println!("\t State root: {}", checkpoint.state_root.green());
+        println!("\t State: {}", state_file.display().to_string().green());
+        println!("\t Block: {}", block_file.display().to_string().green());
+    }
+    if !outcome.failure.is_empty() {
+        println!("{}", "Download errors:".red().bold());
+        for failure in &outcome.failure {
+            println!("\t Endpoint: {}", failure.endpoint.red());
+            println!("\t Error: {}", failure.payload.to_string().red());
+        }
+    }
+}
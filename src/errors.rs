@@ -1,13 +1,125 @@
+use crate::client::SyncIssue;
 use serde::{Deserialize, Serialize};
 
+use strum_macros::IntoStaticStr;
 use thiserror::Error;
 
-#[derive(Debug, Error, Deserialize, Serialize)]
+// Errors related to a single endpoint carry the endpoint, so failures can be categorized by kind
+// and provider from the serialized payload.
+#[derive(Debug, Clone, Error, Deserialize, Serialize, IntoStaticStr)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
 pub enum AppError {
-    #[error("Error: {0}")]
-    EndpointResponseError(String),
-    #[error("Error: {0}")]
-    EndpointsBelowThreshold(String),
-    #[error("Error: {0}")]
-    EndpointsNotFound(String),
+    #[error("Timeout calling {endpoint}: {message}")]
+    Timeout { endpoint: String, message: String },
+    #[error("Could not connect to {endpoint}: {message}")]
+    Connect { endpoint: String, message: String },
+    #[error("Request to {endpoint} failed: {message}")]
+    Request { endpoint: String, message: String },
+    #[error("{endpoint} responded with status code {code}: {body}")]
+    HttpStatus {
+        endpoint: String,
+        code: u16,
+        body: String,
+    },
+    #[error("Could not decode response from {endpoint}: {message}")]
+    Decode { endpoint: String, message: String },
+    #[error("Invalid payload from {endpoint}: {message}")]
+    InvalidPayload { endpoint: String, message: String },
+    #[error("{endpoint} is not on the expected network: {message}")]
+    NetworkMismatch { endpoint: String, message: String },
+    #[error("{endpoint} is not in sync: {}", join_issues(.issues))]
+    NotSynced {
+        endpoint: String,
+        issues: Vec<SyncIssue>,
+    },
+    #[error("{endpoint} returned data that does not match the checkpoint: {message}")]
+    RootMismatch { endpoint: String, message: String },
+    #[error("Quorum was decided before {endpoint} responded")]
+    NotAwaited { endpoint: String },
+    #[error("Error: {message}")]
+    EndpointsBelowThreshold { message: String },
+    #[error("Error: {message}")]
+    EndpointsNotFound { message: String },
+    #[error("Error: {message}")]
+    UnknownNetwork { message: String },
+    #[error("Error: {message}")]
+    InvalidConfig { message: String },
+    #[error("Error: {message}")]
+    InvalidQuorumPolicy { message: String },
+    #[error("Error: {message}")]
+    InvalidStateId { message: String },
+    #[error("Error: {message}")]
+    InvalidSyncCheck { message: String },
+    #[error("Invalid SSZ: {message}")]
+    InvalidSsz { message: String },
+    #[error("Error: {message}")]
+    UnsupportedFork { message: String },
+    #[error("Error: {message}")]
+    NoVerifiedCheckpoint { message: String },
+    #[error("State does not match the checkpoint: {message}")]
+    StateMismatch { message: String },
+    #[error("Error: {message}")]
+    InvalidClient { message: String },
+    #[error("Error: {message}")]
+    InvalidOutputFormat { message: String },
+    #[error("Error: {message}")]
+    Metrics { message: String },
+    #[error("Error: {message}")]
+    InvalidBindAddress { message: String },
+    #[error("Could not listen on {address}: {message}")]
+    Listen { address: String, message: String },
+}
+
+fn join_issues(issues: &[SyncIssue]) -> String {
+    issues
+        .iter()
+        .map(|issue| issue.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl AppError {
+    // replaces the endpoint of endpoint errors, used to report the configured endpoint rather
+    // than the full url that was requested
+    pub fn with_endpoint(mut self, url: &str) -> Self {
+        match &mut self {
+            AppError::Timeout { endpoint, .. }
+            | AppError::Connect { endpoint, .. }
+            | AppError::Request { endpoint, .. }
+            | AppError::HttpStatus { endpoint, .. }
+            | AppError::Decode { endpoint, .. }
+            | AppError::InvalidPayload { endpoint, .. }
+            | AppError::NetworkMismatch { endpoint, .. }
+            | AppError::NotSynced { endpoint, .. }
+            | AppError::RootMismatch { endpoint, .. }
+            | AppError::NotAwaited { endpoint } => *endpoint = url.to_string(),
+            AppError::EndpointsBelowThreshold { .. }
+            | AppError::EndpointsNotFound { .. }
+            | AppError::UnknownNetwork { .. }
+            | AppError::InvalidConfig { .. }
+            | AppError::InvalidQuorumPolicy { .. }
+            | AppError::InvalidStateId { .. }
+            | AppError::InvalidSyncCheck { .. }
+            | AppError::InvalidSsz { .. }
+            | AppError::UnsupportedFork { .. }
+            | AppError::NoVerifiedCheckpoint { .. }
+            | AppError::StateMismatch { .. }
+            | AppError::InvalidClient { .. }
+            | AppError::InvalidOutputFormat { .. }
+            | AppError::Metrics { .. }
+            | AppError::InvalidBindAddress { .. }
+            | AppError::Listen { .. } => {}
+        }
+        self
+    }
+
+    // whether retrying the request that failed with this error may succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            AppError::Timeout { .. } | AppError::Connect { .. } | AppError::Request { .. } => true,
+            AppError::HttpStatus { code, .. } => *code >= 500 || *code == 429,
+            _ => false,
+        }
+    }
 }
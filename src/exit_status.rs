@@ -0,0 +1,52 @@
+use crate::errors::AppError;
+use crate::processor::QuorumStatus;
+use std::process::ExitCode;
+
+// Exit codes of the command line, so scripts can tell a canonical checkpoint from a split vote
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExitStatus {
+    // a checkpoint reached quorum, and matched the state or block when they are verified
+    Canonical = 0,
+    // any other failure, like a downloaded or local state not matching the checkpoint
+    Failure = 1,
+    // invalid arguments or config file, or an unknown network
+    Configuration = 2,
+    // providers returned different checkpoints, or different headers for the checkpoint
+    Conflict = 3,
+    // not enough providers responded for a checkpoint to reach quorum
+    Insufficient = 4,
+}
+
+impl From<QuorumStatus> for ExitStatus {
+    fn from(status: QuorumStatus) -> Self {
+        match status {
+            QuorumStatus::Canonical => ExitStatus::Canonical,
+            QuorumStatus::Conflicting | QuorumStatus::HeaderMismatch => ExitStatus::Conflict,
+            QuorumStatus::Insufficient | QuorumStatus::Failed => ExitStatus::Insufficient,
+        }
+    }
+}
+
+impl From<&AppError> for ExitStatus {
+    fn from(error: &AppError) -> Self {
+        match error {
+            AppError::EndpointsBelowThreshold { .. }
+            | AppError::EndpointsNotFound { .. }
+            | AppError::UnknownNetwork { .. }
+            | AppError::InvalidConfig { .. }
+            | AppError::InvalidQuorumPolicy { .. }
+            | AppError::InvalidStateId { .. }
+            | AppError::InvalidSyncCheck { .. }
+            | AppError::InvalidClient { .. }
+            | AppError::InvalidOutputFormat { .. }
+            | AppError::InvalidBindAddress { .. } => ExitStatus::Configuration,
+            _ => ExitStatus::Failure,
+        }
+    }
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        ExitCode::from(status as u8)
+    }
+}
@@ -0,0 +1,564 @@
+use crate::errors::AppError;
+use crate::network::Network;
+use crate::ssz::SszType;
+use std::str::FromStr;
+use strum_macros::Display;
+
+// Mainnet preset values the sizes of the beacon chain types depend on
+const SLOTS_PER_EPOCH: usize = 32;
+const SLOTS_PER_HISTORICAL_ROOT: usize = 8192;
+const HISTORICAL_ROOTS_LIMIT: usize = 1 << 24;
+const EPOCHS_PER_ETH1_VOTING_PERIOD: usize = 64;
+const VALIDATOR_REGISTRY_LIMIT: usize = 1 << 40;
+const EPOCHS_PER_HISTORICAL_VECTOR: usize = 65536;
+const EPOCHS_PER_SLASHINGS_VECTOR: usize = 8192;
+const MAX_VALIDATORS_PER_COMMITTEE: usize = 2048;
+const MAX_COMMITTEES_PER_SLOT: usize = 64;
+const MAX_PROPOSER_SLASHINGS: usize = 16;
+const MAX_ATTESTER_SLASHINGS: usize = 2;
+const MAX_ATTESTATIONS: usize = 128;
+const MAX_DEPOSITS: usize = 16;
+const MAX_VOLUNTARY_EXITS: usize = 16;
+const DEPOSIT_CONTRACT_TREE_DEPTH: usize = 32;
+const SYNC_COMMITTEE_SIZE: usize = 512;
+const BYTES_PER_LOGS_BLOOM: usize = 256;
+const MAX_EXTRA_DATA_BYTES: usize = 32;
+const MAX_BYTES_PER_TRANSACTION: usize = 1 << 30;
+const MAX_TRANSACTIONS_PER_PAYLOAD: usize = 1 << 20;
+const MAX_WITHDRAWALS_PER_PAYLOAD: usize = 16;
+const MAX_BLS_TO_EXECUTION_CHANGES: usize = 16;
+const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize = 4096;
+const MAX_ATTESTER_SLASHINGS_ELECTRA: usize = 1;
+const MAX_ATTESTATIONS_ELECTRA: usize = 8;
+const PENDING_DEPOSITS_LIMIT: usize = 1 << 27;
+const PENDING_PARTIAL_WITHDRAWALS_LIMIT: usize = 1 << 27;
+const PENDING_CONSOLIDATIONS_LIMIT: usize = 1 << 18;
+const MAX_DEPOSIT_REQUESTS_PER_PAYLOAD: usize = 8192;
+const MAX_WITHDRAWAL_REQUESTS_PER_PAYLOAD: usize = 16;
+const MAX_CONSOLIDATION_REQUESTS_PER_PAYLOAD: usize = 2;
+const MIN_SEED_LOOKAHEAD: usize = 1;
+
+// offset of fork.current_version in the serialization of a BeaconState, the same for every fork:
+// genesis_time, genesis_validators_root, slot and fork.previous_version come before it
+const STATE_CURRENT_VERSION_OFFSET: usize = 8 + 32 + 8 + 4;
+
+// The forks of the beacon chain, in activation order, named as in the Eth-Consensus-Version
+// header of the beacon API
+#[derive(Display, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[strum(serialize_all = "lowercase")]
+pub enum ForkName {
+    Phase0,
+    Altair,
+    Bellatrix,
+    Capella,
+    Deneb,
+    Electra,
+    Fulu,
+}
+
+impl FromStr for ForkName {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ForkName::ALL
+            .into_iter()
+            .find(|fork| fork.to_string() == s.trim().to_lowercase())
+            .ok_or(AppError::UnsupportedFork {
+                message: format!("Unknown fork {s}"),
+            })
+    }
+}
+
+fn uint64() -> SszType {
+    SszType::Uint(8)
+}
+
+fn bytes32() -> SszType {
+    SszType::Bytes(32)
+}
+
+fn container(fields: Vec<(&'static str, SszType)>) -> SszType {
+    SszType::Container(fields)
+}
+
+fn checkpoint() -> SszType {
+    container(vec![("epoch", uint64()), ("root", bytes32())])
+}
+
+fn eth1_data() -> SszType {
+    container(vec![
+        ("deposit_root", bytes32()),
+        ("deposit_count", uint64()),
+        ("block_hash", bytes32()),
+    ])
+}
+
+fn attestation_data() -> SszType {
+    container(vec![
+        ("slot", uint64()),
+        ("index", uint64()),
+        ("beacon_block_root", bytes32()),
+        ("source", checkpoint()),
+        ("target", checkpoint()),
+    ])
+}
+
+pub fn beacon_block_header() -> SszType {
+    container(vec![
+        ("slot", uint64()),
+        ("proposer_index", uint64()),
+        ("parent_root", bytes32()),
+        ("state_root", bytes32()),
+        ("body_root", bytes32()),
+    ])
+}
+
+fn signed_beacon_block_header() -> SszType {
+    container(vec![
+        ("message", beacon_block_header()),
+        ("signature", SszType::Bytes(96)),
+    ])
+}
+
+fn indexed_attestation(max_indices: usize) -> SszType {
+    container(vec![
+        ("attesting_indices", SszType::list(uint64(), max_indices)),
+        ("data", attestation_data()),
+        ("signature", SszType::Bytes(96)),
+    ])
+}
+
+fn sync_committee() -> SszType {
+    container(vec![
+        (
+            "pubkeys",
+            SszType::vector(SszType::Bytes(48), SYNC_COMMITTEE_SIZE),
+        ),
+        ("aggregate_pubkey", SszType::Bytes(48)),
+    ])
+}
+
+impl ForkName {
+    pub const ALL: [ForkName; 7] = [
+        ForkName::Phase0,
+        ForkName::Altair,
+        ForkName::Bellatrix,
+        ForkName::Capella,
+        ForkName::Deneb,
+        ForkName::Electra,
+        ForkName::Fulu,
+    ];
+
+    // The fork a state belongs to, from the current version of its fork field. The fork versions
+    // of the network are expected to be listed from genesis, one per fork.
+    pub fn of_state(network: &Network, state: &[u8]) -> Result<Self, AppError> {
+        if network.slots_per_epoch != SLOTS_PER_EPOCH as u64 {
+            return Err(AppError::UnsupportedFork {
+                message: "Only networks using the mainnet preset are supported".to_string(),
+            });
+        }
+        let version = state
+            .get(STATE_CURRENT_VERSION_OFFSET..STATE_CURRENT_VERSION_OFFSET + 4)
+            .map(|version| format!("0x{}", hex::encode(version)))
+            .ok_or(AppError::InvalidSsz {
+                message: "State is too short".to_string(),
+            })?;
+        network
+            .fork_versions
+            .iter()
+            .position(|known| known.eq_ignore_ascii_case(&version))
+            .and_then(|index| ForkName::ALL.get(index).copied())
+            .ok_or(AppError::UnsupportedFork {
+                message: format!(
+                    "Fork version {version} is not one of the fork versions of the network"
+                ),
+            })
+    }
+
+    fn execution_payload(&self, header: bool) -> SszType {
+        let mut fields = vec![
+            ("parent_hash", bytes32()),
+            ("fee_recipient", SszType::Bytes(20)),
+            ("state_root", bytes32()),
+            ("receipts_root", bytes32()),
+            ("logs_bloom", SszType::Bytes(BYTES_PER_LOGS_BLOOM)),
+            ("prev_randao", bytes32()),
+            ("block_number", uint64()),
+            ("gas_limit", uint64()),
+            ("gas_used", uint64()),
+            ("timestamp", uint64()),
+            ("extra_data", SszType::ByteList(MAX_EXTRA_DATA_BYTES)),
+            ("base_fee_per_gas", SszType::Uint(32)),
+            ("block_hash", bytes32()),
+        ];
+        if header {
+            fields.push(("transactions_root", bytes32()));
+        } else {
+            fields.push((
+                "transactions",
+                SszType::list(
+                    SszType::ByteList(MAX_BYTES_PER_TRANSACTION),
+                    MAX_TRANSACTIONS_PER_PAYLOAD,
+                ),
+            ));
+        }
+        if *self >= ForkName::Capella {
+            if header {
+                fields.push(("withdrawals_root", bytes32()));
+            } else {
+                let withdrawal = container(vec![
+                    ("index", uint64()),
+                    ("validator_index", uint64()),
+                    ("address", SszType::Bytes(20)),
+                    ("amount", uint64()),
+                ]);
+                fields.push((
+                    "withdrawals",
+                    SszType::list(withdrawal, MAX_WITHDRAWALS_PER_PAYLOAD),
+                ));
+            }
+        }
+        if *self >= ForkName::Deneb {
+            fields.push(("blob_gas_used", uint64()));
+            fields.push(("excess_blob_gas", uint64()));
+        }
+        container(fields)
+    }
+
+    pub fn beacon_state(&self) -> SszType {
+        let validator = container(vec![
+            ("pubkey", SszType::Bytes(48)),
+            ("withdrawal_credentials", bytes32()),
+            ("effective_balance", uint64()),
+            ("slashed", SszType::Uint(1)),
+            ("activation_eligibility_epoch", uint64()),
+            ("activation_epoch", uint64()),
+            ("exit_epoch", uint64()),
+            ("withdrawable_epoch", uint64()),
+        ]);
+        let mut fields = vec![
+            ("genesis_time", uint64()),
+            ("genesis_validators_root", bytes32()),
+            ("slot", uint64()),
+            (
+                "fork",
+                container(vec![
+                    ("previous_version", SszType::Bytes(4)),
+                    ("current_version", SszType::Bytes(4)),
+                    ("epoch", uint64()),
+                ]),
+            ),
+            ("latest_block_header", beacon_block_header()),
+            (
+                "block_roots",
+                SszType::vector(bytes32(), SLOTS_PER_HISTORICAL_ROOT),
+            ),
+            (
+                "state_roots",
+                SszType::vector(bytes32(), SLOTS_PER_HISTORICAL_ROOT),
+            ),
+            (
+                "historical_roots",
+                SszType::list(bytes32(), HISTORICAL_ROOTS_LIMIT),
+            ),
+            ("eth1_data", eth1_data()),
+            (
+                "eth1_data_votes",
+                SszType::list(eth1_data(), EPOCHS_PER_ETH1_VOTING_PERIOD * SLOTS_PER_EPOCH),
+            ),
+            ("eth1_deposit_index", uint64()),
+            (
+                "validators",
+                SszType::list(validator, VALIDATOR_REGISTRY_LIMIT),
+            ),
+            (
+                "balances",
+                SszType::list(uint64(), VALIDATOR_REGISTRY_LIMIT),
+            ),
+            (
+                "randao_mixes",
+                SszType::vector(bytes32(), EPOCHS_PER_HISTORICAL_VECTOR),
+            ),
+            (
+                "slashings",
+                SszType::vector(uint64(), EPOCHS_PER_SLASHINGS_VECTOR),
+            ),
+        ];
+        if *self == ForkName::Phase0 {
+            let pending_attestation = container(vec![
+                (
+                    "aggregation_bits",
+                    SszType::Bitlist(MAX_VALIDATORS_PER_COMMITTEE),
+                ),
+                ("data", attestation_data()),
+                ("inclusion_delay", uint64()),
+                ("proposer_index", uint64()),
+            ]);
+            let pending_attestations =
+                SszType::list(pending_attestation, MAX_ATTESTATIONS * SLOTS_PER_EPOCH);
+            fields.push(("previous_epoch_attestations", pending_attestations.clone()));
+            fields.push(("current_epoch_attestations", pending_attestations));
+        } else {
+            let participation = SszType::list(SszType::Uint(1), VALIDATOR_REGISTRY_LIMIT);
+            fields.push(("previous_epoch_participation", participation.clone()));
+            fields.push(("current_epoch_participation", participation));
+        }
+        fields.extend([
+            ("justification_bits", SszType::Bitvector(4)),
+            ("previous_justified_checkpoint", checkpoint()),
+            ("current_justified_checkpoint", checkpoint()),
+            ("finalized_checkpoint", checkpoint()),
+        ]);
+        if *self >= ForkName::Altair {
+            fields.extend([
+                (
+                    "inactivity_scores",
+                    SszType::list(uint64(), VALIDATOR_REGISTRY_LIMIT),
+                ),
+                ("current_sync_committee", sync_committee()),
+                ("next_sync_committee", sync_committee()),
+            ]);
+        }
+        if *self >= ForkName::Bellatrix {
+            fields.push((
+                "latest_execution_payload_header",
+                self.execution_payload(true),
+            ));
+        }
+        if *self >= ForkName::Capella {
+            let historical_summary = container(vec![
+                ("block_summary_root", bytes32()),
+                ("state_summary_root", bytes32()),
+            ]);
+            fields.extend([
+                ("next_withdrawal_index", uint64()),
+                ("next_withdrawal_validator_index", uint64()),
+                (
+                    "historical_summaries",
+                    SszType::list(historical_summary, HISTORICAL_ROOTS_LIMIT),
+                ),
+            ]);
+        }
+        if *self >= ForkName::Electra {
+            let pending_deposit = container(vec![
+                ("pubkey", SszType::Bytes(48)),
+                ("withdrawal_credentials", bytes32()),
+                ("amount", uint64()),
+                ("signature", SszType::Bytes(96)),
+                ("slot", uint64()),
+            ]);
+            let pending_partial_withdrawal = container(vec![
+                ("validator_index", uint64()),
+                ("amount", uint64()),
+                ("withdrawable_epoch", uint64()),
+            ]);
+            let pending_consolidation =
+                container(vec![("source_index", uint64()), ("target_index", uint64())]);
+            fields.extend([
+                ("deposit_requests_start_index", uint64()),
+                ("deposit_balance_to_consume", uint64()),
+                ("exit_balance_to_consume", uint64()),
+                ("earliest_exit_epoch", uint64()),
+                ("consolidation_balance_to_consume", uint64()),
+                ("earliest_consolidation_epoch", uint64()),
+                (
+                    "pending_deposits",
+                    SszType::list(pending_deposit, PENDING_DEPOSITS_LIMIT),
+                ),
+                (
+                    "pending_partial_withdrawals",
+                    SszType::list(
+                        pending_partial_withdrawal,
+                        PENDING_PARTIAL_WITHDRAWALS_LIMIT,
+                    ),
+                ),
+                (
+                    "pending_consolidations",
+                    SszType::list(pending_consolidation, PENDING_CONSOLIDATIONS_LIMIT),
+                ),
+            ]);
+        }
+        if *self >= ForkName::Fulu {
+            fields.push((
+                "proposer_lookahead",
+                SszType::vector(uint64(), (MIN_SEED_LOOKAHEAD + 1) * SLOTS_PER_EPOCH),
+            ));
+        }
+        container(fields)
+    }
+
+    pub fn beacon_block_body(&self) -> SszType {
+        let (max_attester_slashings, max_attestations, max_indices) = if *self >= ForkName::Electra
+        {
+            (
+                MAX_ATTESTER_SLASHINGS_ELECTRA,
+                MAX_ATTESTATIONS_ELECTRA,
+                MAX_VALIDATORS_PER_COMMITTEE * MAX_COMMITTEES_PER_SLOT,
+            )
+        } else {
+            (
+                MAX_ATTESTER_SLASHINGS,
+                MAX_ATTESTATIONS,
+                MAX_VALIDATORS_PER_COMMITTEE,
+            )
+        };
+        let mut attestation = vec![
+            ("aggregation_bits", SszType::Bitlist(max_indices)),
+            ("data", attestation_data()),
+            ("signature", SszType::Bytes(96)),
+        ];
+        if *self >= ForkName::Electra {
+            attestation.push((
+                "committee_bits",
+                SszType::Bitvector(MAX_COMMITTEES_PER_SLOT),
+            ));
+        }
+        let proposer_slashing = container(vec![
+            ("signed_header_1", signed_beacon_block_header()),
+            ("signed_header_2", signed_beacon_block_header()),
+        ]);
+        let attester_slashing = container(vec![
+            ("attestation_1", indexed_attestation(max_indices)),
+            ("attestation_2", indexed_attestation(max_indices)),
+        ]);
+        let deposit = container(vec![
+            (
+                "proof",
+                SszType::vector(bytes32(), DEPOSIT_CONTRACT_TREE_DEPTH + 1),
+            ),
+            (
+                "data",
+                container(vec![
+                    ("pubkey", SszType::Bytes(48)),
+                    ("withdrawal_credentials", bytes32()),
+                    ("amount", uint64()),
+                    ("signature", SszType::Bytes(96)),
+                ]),
+            ),
+        ]);
+        let signed_voluntary_exit = container(vec![
+            (
+                "message",
+                container(vec![("epoch", uint64()), ("validator_index", uint64())]),
+            ),
+            ("signature", SszType::Bytes(96)),
+        ]);
+        let mut fields = vec![
+            ("randao_reveal", SszType::Bytes(96)),
+            ("eth1_data", eth1_data()),
+            ("graffiti", bytes32()),
+            (
+                "proposer_slashings",
+                SszType::list(proposer_slashing, MAX_PROPOSER_SLASHINGS),
+            ),
+            (
+                "attester_slashings",
+                SszType::list(attester_slashing, max_attester_slashings),
+            ),
+            (
+                "attestations",
+                SszType::list(container(attestation), max_attestations),
+            ),
+            ("deposits", SszType::list(deposit, MAX_DEPOSITS)),
+            (
+                "voluntary_exits",
+                SszType::list(signed_voluntary_exit, MAX_VOLUNTARY_EXITS),
+            ),
+        ];
+        if *self >= ForkName::Altair {
+            fields.push((
+                "sync_aggregate",
+                container(vec![
+                    (
+                        "sync_committee_bits",
+                        SszType::Bitvector(SYNC_COMMITTEE_SIZE),
+                    ),
+                    ("sync_committee_signature", SszType::Bytes(96)),
+                ]),
+            ));
+        }
+        if *self >= ForkName::Bellatrix {
+            fields.push(("execution_payload", self.execution_payload(false)));
+        }
+        if *self >= ForkName::Capella {
+            let signed_bls_to_execution_change = container(vec![
+                (
+                    "message",
+                    container(vec![
+                        ("validator_index", uint64()),
+                        ("from_bls_pubkey", SszType::Bytes(48)),
+                        ("to_execution_address", SszType::Bytes(20)),
+                    ]),
+                ),
+                ("signature", SszType::Bytes(96)),
+            ]);
+            fields.push((
+                "bls_to_execution_changes",
+                SszType::list(signed_bls_to_execution_change, MAX_BLS_TO_EXECUTION_CHANGES),
+            ));
+        }
+        if *self >= ForkName::Deneb {
+            fields.push((
+                "blob_kzg_commitments",
+                SszType::list(SszType::Bytes(48), MAX_BLOB_COMMITMENTS_PER_BLOCK),
+            ));
+        }
+        if *self >= ForkName::Electra {
+            let deposit_request = container(vec![
+                ("pubkey", SszType::Bytes(48)),
+                ("withdrawal_credentials", bytes32()),
+                ("amount", uint64()),
+                ("signature", SszType::Bytes(96)),
+                ("index", uint64()),
+            ]);
+            let withdrawal_request = container(vec![
+                ("source_address", SszType::Bytes(20)),
+                ("validator_pubkey", SszType::Bytes(48)),
+                ("amount", uint64()),
+            ]);
+            let consolidation_request = container(vec![
+                ("source_address", SszType::Bytes(20)),
+                ("source_pubkey", SszType::Bytes(48)),
+                ("target_pubkey", SszType::Bytes(48)),
+            ]);
+            fields.push((
+                "execution_requests",
+                container(vec![
+                    (
+                        "deposits",
+                        SszType::list(deposit_request, MAX_DEPOSIT_REQUESTS_PER_PAYLOAD),
+                    ),
+                    (
+                        "withdrawals",
+                        SszType::list(withdrawal_request, MAX_WITHDRAWAL_REQUESTS_PER_PAYLOAD),
+                    ),
+                    (
+                        "consolidations",
+                        SszType::list(
+                            consolidation_request,
+                            MAX_CONSOLIDATION_REQUESTS_PER_PAYLOAD,
+                        ),
+                    ),
+                ]),
+            ));
+        }
+        container(fields)
+    }
+
+    pub fn beacon_block(&self) -> SszType {
+        container(vec![
+            ("slot", uint64()),
+            ("proposer_index", uint64()),
+            ("parent_root", bytes32()),
+            ("state_root", bytes32()),
+            ("body", self.beacon_block_body()),
+        ])
+    }
+
+    pub fn signed_beacon_block(&self) -> SszType {
+        container(vec![
+            ("message", self.beacon_block()),
+            ("signature", SszType::Bytes(96)),
+        ])
+    }
+}
@@ -1,5 +1,15 @@
 pub mod args;
 pub mod checkpoint_server;
 pub mod client;
+pub mod download;
 pub mod errors;
+pub mod exit_status;
+pub mod forks;
+pub mod metrics;
+pub mod network;
 pub mod processor;
+pub mod quorum;
+pub mod ssz;
+pub mod verify;
+pub mod watch;
+pub mod wss;
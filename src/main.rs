@@ -1,73 +1,270 @@
+use std::collections::HashSet;
+use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
 
 use clap::Parser;
 
-use checkpointq_lib::args::{Cli, SubCommands};
-use checkpointq_lib::args::Network::Mainnet;
+use checkpointq_lib::args::{Cli, SharedCommands, SubCommands};
 use checkpointq_lib::checkpoint_server;
+use checkpointq_lib::checkpoint_server::Listener;
 use checkpointq_lib::client::{CheckpointClient, EndpointsConfig};
-use checkpointq_lib::client::StateId;
+use checkpointq_lib::download::{download_checkpoint, print_download_outcome};
 use checkpointq_lib::errors::AppError;
-use checkpointq_lib::processor::print_result;
+use checkpointq_lib::exit_status::ExitStatus;
+use checkpointq_lib::processor::{
+    print_all_checkpoints_output, print_output, print_result, CheckpointKind,
+};
+use checkpointq_lib::verify::{
+    epoch_start_slot, is_block_state, print_state_verification, verify_state,
+};
+use checkpointq_lib::watch::watch;
+use checkpointq_lib::wss::{print_ws_checkpoint, WsCheckpoint};
 
-fn parse_endpoint_config(
-    endpoints_path: PathBuf,
-) -> Result<EndpointsConfig, Box<dyn std::error::Error>> {
+fn parse_endpoint_config(endpoints_path: PathBuf) -> Result<EndpointsConfig, AppError> {
+    let file = std::fs::File::open(&endpoints_path).map_err(|e| AppError::InvalidConfig {
+        message: format!("Could not open {}: {e}", endpoints_path.display()),
+    })?;
     let endpoints_config: EndpointsConfig =
-        serde_yaml::from_reader(std::fs::File::open(endpoints_path)?)?;
+        serde_yaml::from_reader(file).map_err(|e| AppError::InvalidConfig {
+            message: format!("Could not parse {}: {e}", endpoints_path.display()),
+        })?;
     let above_threshold = endpoints_config
         .endpoints
         .values()
         .all(|value| value.len() >= 3);
-    if above_threshold {
+    if !above_threshold {
+        return Err(AppError::EndpointsBelowThreshold {
+            message: "Number of endpoints provided for networks in the config must be at least 3"
+                .to_string(),
+        });
+    }
+    for network in endpoints_config.endpoints.keys() {
+        endpoints_config.network(network)?;
+    }
+    let min_distinct_operators = endpoints_config.min_distinct_operators.unwrap_or(1);
+    let enough_operators = endpoints_config.endpoints.values().all(|value| {
+        value
+            .iter()
+            .map(|endpoint| endpoint.operator())
+            .collect::<HashSet<&str>>()
+            .len() as u64
+            >= min_distinct_operators
+    });
+    if enough_operators {
         Ok(endpoints_config)
     } else {
-        Err(Box::new(AppError::EndpointsBelowThreshold(
-            "Number of endpoints provided for networks in the config must be at least 3"
-                .to_string(),
-        )))
+        Err(AppError::EndpointsBelowThreshold {
+            message: format!(
+                "Number of distinct operators for networks in the config must be at least {min_distinct_operators}"
+            ),
+        })
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let input = Cli::parse();
-    let client = reqwest::Client::new();
-    let state_id: StateId = StateId::Finalized; // only finalized supported for now
+fn build_checkpoint_client(
+    shared: SharedCommands,
+) -> Result<CheckpointClient<reqwest::Client>, AppError> {
+    let endpoints_path = shared.endpoints.clone().unwrap_or("endpoints.yaml".into());
+    let endpoints_config: EndpointsConfig = parse_endpoint_config(endpoints_path)?;
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(shared.connect_timeout))
+        .build()
+        .map_err(|e| AppError::InvalidConfig {
+            message: e.to_string(),
+        })?;
 
+    let mut checkpoint_client =
+        CheckpointClient::new(client, shared.state_id.clone(), endpoints_config)
+            .with_request_config(shared.request_config())
+            .with_early_exit(shared.early_exit)
+            .with_network_check(!shared.skip_network_check)
+            .with_sync_check(shared.sync_check);
+    if let Some(quorum_policy) = shared.quorum {
+        checkpoint_client = checkpoint_client.with_quorum_policy(quorum_policy);
+    }
+    Ok(checkpoint_client)
+}
+
+async fn run(input: Cli) -> Result<ExitStatus, Box<dyn std::error::Error>> {
     match input.subcommands {
         Some(subcommand) => {
             match subcommand {
                 SubCommands::ServerCliCommands(server_command) => {
                     // server run
-                    let endpoints_path = server_command
-                        .shared
-                        .endpoints
-                        .unwrap_or("endpoints.yaml".into());
                     let port = server_command.port;
-                    let endpoints_config: EndpointsConfig = parse_endpoint_config(endpoints_path)?;
-
-                    let checkpoint_client =
-                        CheckpointClient::new(client, state_id, endpoints_config);
+                    let mut listeners: Vec<Listener> = server_command
+                        .bind
+                        .iter()
+                        .map(|bind| Listener::Tcp(bind.socket_addr(port)))
+                        .collect();
+                    listeners.extend(server_command.unix_socket.map(Listener::Unix));
+                    let checkpoint_client = build_checkpoint_client(server_command.shared)?;
                     let server =
-                        checkpoint_server::CheckPointMiddleware::new(checkpoint_client, port);
-                    server.serve().await;
+                        checkpoint_server::CheckPointMiddleware::new(checkpoint_client, port)
+                            .with_cache(!server_command.no_cache)
+                            .with_ready_max_age(
+                                server_command.ready_max_age.map(Duration::from_secs),
+                            )
+                            .with_listeners(listeners)
+                            .with_admin_listeners(
+                                server_command
+                                    .admin_bind
+                                    .into_iter()
+                                    .map(Listener::Tcp)
+                                    .collect(),
+                            );
+                    server.serve().await?;
+                    Ok(ExitStatus::Canonical)
+                }
+                SubCommands::DownloadCliCommands(download_command) => {
+                    let network = input.network;
+                    let checkpoint_client = build_checkpoint_client(download_command.shared)?;
+                    let result = checkpoint_client
+                        .fetch_finality_checkpoints(&network)
+                        .await?;
+                    let status = ExitStatus::from(result.status());
+                    if status != ExitStatus::Canonical {
+                        print_result(result, input.verbose);
+                        return Ok(status);
+                    }
+                    let outcome = download_checkpoint(
+                        &checkpoint_client,
+                        &network,
+                        &result,
+                        Duration::from_secs(download_command.download_timeout),
+                    )
+                    .await;
+                    print_result(result, input.verbose);
+                    let outcome = outcome?;
+                    if let Some(checkpoint) = &outcome.checkpoint {
+                        std::fs::write(&download_command.state_file, &checkpoint.state)?;
+                        std::fs::write(&download_command.block_file, &checkpoint.block)?;
+                    }
+                    print_download_outcome(
+                        &outcome,
+                        &download_command.state_file,
+                        &download_command.block_file,
+                    );
+                    if outcome.checkpoint.is_none() {
+                        return Err(AppError::NoVerifiedCheckpoint {
+                            message: "None of the providers returned a state and block matching the checkpoint".to_string(),
+                        }
+                        .into());
+                    }
+                    Ok(ExitStatus::Canonical)
+                }
+                SubCommands::VerifyStateCliCommands(verify_command) => {
+                    let network = input.network;
+                    let state = std::fs::read(&verify_command.file)?;
+                    let checkpoint_client = build_checkpoint_client(verify_command.shared)?;
+                    let result = checkpoint_client
+                        .fetch_finality_checkpoints(&network)
+                        .await?;
+                    let status = ExitStatus::from(result.status());
+                    if status != ExitStatus::Canonical {
+                        print_result(result, input.verbose);
+                        return Ok(status);
+                    }
+                    let network_config = checkpoint_client.network(&network)?;
+                    // a state advanced past the finalized block is checked against the state
+                    // root the providers agree on at the start of the epoch
+                    let verification = async {
+                        let epoch_start_state_root = if is_block_state(&network_config, &state)? {
+                            None
+                        } else {
+                            let slot = epoch_start_slot(&network_config, &result)?;
+                            Some(checkpoint_client.fetch_state_root(&result, slot).await?)
+                        };
+                        verify_state(
+                            &network_config,
+                            &result,
+                            &state,
+                            epoch_start_state_root.as_deref(),
+                        )
+                    }
+                    .await;
+                    print_result(result, input.verbose);
+                    print_state_verification(&verification?);
+                    Ok(ExitStatus::Canonical)
+                }
+                SubCommands::WatchCliCommands(watch_command) => {
+                    let checkpoint_client = build_checkpoint_client(watch_command.shared)?;
+                    watch(
+                        &checkpoint_client,
+                        &input.network,
+                        watch_command.stall_epochs,
+                    )
+                    .await?;
+                    Ok(ExitStatus::Canonical)
                 }
             }
         }
         None => {
             // Normal run
             let is_verbose = input.verbose;
-            let endpoints_path = input.shared.endpoints.unwrap_or("endpoints.yaml".into());
-            let endpoints_config: EndpointsConfig = parse_endpoint_config(endpoints_path)?;
+            let network = input.network;
+            let checkpoint_client = build_checkpoint_client(input.shared)?;
+            if input.all_checkpoints {
+                let results = checkpoint_client.fetch_all_checkpoints(&network).await?;
+                // the exit status is the one of the finalized checkpoint
+                let status = results
+                    .iter()
+                    .find(|result| result.checkpoint == CheckpointKind::Finalized)
+                    .map(|result| ExitStatus::from(result.status()))
+                    .unwrap_or(ExitStatus::Failure);
+                print_all_checkpoints_output(results, input.output, is_verbose);
+                Ok(status)
+            } else if input.wss || input.client.is_some() {
+                let result = checkpoint_client
+                    .fetch_finality_checkpoints(&network)
+                    .await?;
+                let status = ExitStatus::from(result.status());
+                match WsCheckpoint::from_result(&result) {
+                    Ok(ws_checkpoint) => {
+                        print_ws_checkpoint(&ws_checkpoint, input.client);
+                        Ok(status)
+                    }
+                    Err(error) => {
+                        eprintln!("{error}");
+                        // nothing was printed, so scripts must not see a success even when the
+                        // quorum status is canonical
+                        if status == ExitStatus::Canonical {
+                            Ok(ExitStatus::Failure)
+                        } else {
+                            Ok(status)
+                        }
+                    }
+                }
+            } else {
+                let result = checkpoint_client
+                    .fetch_finality_checkpoints(&network)
+                    .await?;
+                let status = ExitStatus::from(result.status());
+                print_output(result, input.output, is_verbose);
+                Ok(status)
+            }
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let input = Cli::parse();
+    if std::env::var_os("NO_COLOR").is_some() || !std::io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
 
-            let network = input.network.unwrap_or(Mainnet);
-            let checkpoint_client = CheckpointClient::new(client, state_id, endpoints_config);
-            let result = checkpoint_client
-                .fetch_finality_checkpoints(network)
-                .await?;
-            print_result(result, is_verbose);
+    match run(input).await {
+        Ok(status) => status.into(),
+        Err(error) => {
+            eprintln!("{error}");
+            error
+                .downcast_ref::<AppError>()
+                .map(ExitStatus::from)
+                .unwrap_or(ExitStatus::Failure)
+                .into()
         }
     }
-    Ok(())
 }
@@ -0,0 +1,203 @@
+use crate::errors::AppError;
+use crate::processor::{DisplayableResult, QuorumStatus};
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::fmt;
+use std::time::Duration;
+
+// Prometheus metrics of the requests made to the providers and of the quorum results, exposed
+// by the server on /metrics
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    failures: IntCounterVec,
+    agreement_ratio: GaugeVec,
+    finalized_epoch: IntGaugeVec,
+    conflicting_roots: IntGaugeVec,
+    cache_hits: IntCounterVec,
+    cache_misses: IntCounterVec,
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics").finish_non_exhaustive()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(registry: &Registry, metric: T) -> T {
+    registry
+        .register(Box::new(metric.clone()))
+        .expect("metric names are unique");
+    metric
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("checkpointq".to_string()), None)
+            .expect("the prefix is valid");
+        let counter = |name: &str, help: &str, labels: &[&str]| {
+            register(
+                &registry,
+                IntCounterVec::new(Opts::new(name, help), labels).expect("valid counter"),
+            )
+        };
+        let int_gauge = |name: &str, help: &str| {
+            register(
+                &registry,
+                IntGaugeVec::new(Opts::new(name, help), &["network"]).expect("valid gauge"),
+            )
+        };
+        Metrics {
+            requests: counter(
+                "provider_requests_total",
+                "Requests made to the providers, retries included",
+                &["endpoint", "resource"],
+            ),
+            request_duration: register(
+                &registry,
+                HistogramVec::new(
+                    HistogramOpts::new(
+                        "provider_request_duration_seconds",
+                        "Time taken by the requests made to the providers",
+                    ),
+                    &["endpoint", "resource"],
+                )
+                .expect("valid histogram"),
+            ),
+            failures: counter(
+                "provider_failures_total",
+                "Providers that failed to vote in a quorum round, by kind of error",
+                &["network", "endpoint", "kind"],
+            ),
+            agreement_ratio: register(
+                &registry,
+                GaugeVec::new(
+                    Opts::new(
+                        "agreement_ratio",
+                        "Share of the quorum weight behind the most common finalized checkpoint",
+                    ),
+                    &["network"],
+                )
+                .expect("valid gauge"),
+            ),
+            finalized_epoch: int_gauge(
+                "finalized_epoch",
+                "Epoch of the last finalized checkpoint that reached quorum",
+            ),
+            conflicting_roots: int_gauge(
+                "conflicting_roots",
+                "Finalized roots returned by the providers other than the one that reached quorum",
+            ),
+            cache_hits: counter(
+                "cache_hits_total",
+                "Requests to the server answered from the cache",
+                &["network"],
+            ),
+            cache_misses: counter(
+                "cache_misses_total",
+                "Requests to the server the cache could not answer",
+                &["network"],
+            ),
+            registry,
+        }
+    }
+
+    pub fn observe_request(&self, endpoint: &str, path: &str, duration: Duration) {
+        let labels = [endpoint, resource(path)];
+        self.requests.with_label_values(&labels).inc();
+        self.request_duration
+            .with_label_values(&labels)
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn observe_failures(&self, network: &str, result: &DisplayableResult) {
+        for failure in &result.failure {
+            let kind: &'static str = (&failure.payload).into();
+            self.failures
+                .with_label_values(&[network, &failure.endpoint, kind])
+                .inc();
+        }
+    }
+
+    // the gauges describe the network as seen with the default options, so only the results
+    // fetched with them are observed
+    pub fn observe_result(&self, network: &str, result: &DisplayableResult) {
+        self.agreement_ratio
+            .with_label_values(&[network])
+            .set(agreement_ratio(result));
+        let canonical = result.status() == QuorumStatus::Canonical;
+        if let Some(epoch) = result
+            .canonical_checkpoint()
+            .filter(|_| canonical)
+            .and_then(|block_info| block_info.epoch.parse::<i64>().ok())
+        {
+            self.finalized_epoch
+                .with_label_values(&[network])
+                .set(epoch);
+        }
+        let conflicting = result.tally.len() - usize::from(result.canonical.is_some());
+        self.conflicting_roots
+            .with_label_values(&[network])
+            .set(conflicting as i64);
+    }
+
+    pub fn observe_cache(&self, network: &str, hit: bool) {
+        if hit {
+            self.cache_hits.with_label_values(&[network]).inc();
+        } else {
+            self.cache_misses.with_label_values(&[network]).inc();
+        }
+    }
+
+    // the metrics in the prometheus text format
+    pub fn encode(&self) -> Result<String, AppError> {
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| AppError::Metrics {
+                message: e.to_string(),
+            })?;
+        String::from_utf8(buffer).map_err(|e| AppError::Metrics {
+            message: e.to_string(),
+        })
+    }
+}
+
+// the beacon API resource a request is for, so the urls of the providers do not multiply the
+// number of series
+fn resource(path: &str) -> &'static str {
+    const RESOURCES: [(&str, &str); 7] = [
+        ("/finality_checkpoints", "finality_checkpoints"),
+        ("/beacon/genesis", "genesis"),
+        ("/fork", "fork"),
+        ("/node/syncing", "syncing"),
+        ("/beacon/headers/", "headers"),
+        ("/debug/beacon/states/", "states"),
+        ("/beacon/blocks/", "blocks"),
+    ];
+    RESOURCES
+        .iter()
+        .find(|(pattern, _)| path.contains(pattern))
+        .map(|(_, resource)| *resource)
+        .unwrap_or("other")
+}
+
+// weight behind the most common finalized checkpoint over the weight of every endpoint queried,
+// as the quorum is computed
+fn agreement_ratio(result: &DisplayableResult) -> f64 {
+    match result.tally.first() {
+        Some(tally) if result.configured_weight > 0 => {
+            tally.weight as f64 / result.configured_weight as f64
+        }
+        _ => 0.0,
+    }
+}
@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// A network checkpoints can be requested for. The built-in networks can be overridden, and new
+// ones added, in the networks section of the config file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Network {
+    pub genesis_validators_root: String,
+    pub genesis_time: u64,
+    #[serde(default = "default_seconds_per_slot")]
+    pub seconds_per_slot: u64,
+    #[serde(default = "default_slots_per_epoch")]
+    pub slots_per_epoch: u64,
+    // fork versions the network has gone through, from genesis. The current fork version of a
+    // provider must be the one scheduled for the current epoch, it is not checked when empty.
+    #[serde(default)]
+    pub fork_versions: Vec<String>,
+    // epochs the fork versions activate at, in the same order. Without them only the last fork
+    // version is accepted.
+    #[serde(default)]
+    pub fork_epochs: Vec<u64>,
+}
+
+fn default_seconds_per_slot() -> u64 {
+    12
+}
+
+fn default_slots_per_epoch() -> u64 {
+    32
+}
+
+impl Network {
+    fn ethereum(genesis_validators_root: &str, genesis_time: u64, forks: &[(&str, u64)]) -> Self {
+        Network {
+            genesis_validators_root: genesis_validators_root.to_string(),
+            genesis_time,
+            seconds_per_slot: default_seconds_per_slot(),
+            slots_per_epoch: default_slots_per_epoch(),
+            fork_versions: forks.iter().map(|(v, _)| v.to_string()).collect(),
+            fork_epochs: forks.iter().map(|(_, epoch)| *epoch).collect(),
+        }
+    }
+
+    // the epoch the network is in according to the wall clock, 0 before genesis
+    pub fn current_epoch(&self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        now.saturating_sub(self.genesis_time) / self.seconds_per_epoch()
+    }
+
+    pub fn seconds_per_epoch(&self) -> u64 {
+        (self.seconds_per_slot * self.slots_per_epoch).max(1)
+    }
+
+    // the wall clock time the first slot of the epoch starts at
+    pub fn epoch_start(&self, epoch: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.genesis_time + epoch * self.seconds_per_epoch())
+    }
+
+    // time until one slot after the start of the next epoch, when the first block of the epoch
+    // had the time to be processed and the checkpoints can have moved
+    pub fn until_next_epoch(&self) -> Duration {
+        let next =
+            self.epoch_start(self.current_epoch() + 1) + Duration::from_secs(self.seconds_per_slot);
+        next.duration_since(SystemTime::now()).unwrap_or_default()
+    }
+
+    // the fork versions a provider can report at the epoch: the scheduled one, and during the
+    // first epoch of a fork the previous one as well, as the provider may not have processed the
+    // first block of the fork yet
+    pub fn fork_versions_at(&self, epoch: u64) -> Vec<&str> {
+        if self.fork_epochs.len() != self.fork_versions.len() {
+            return self
+                .fork_versions
+                .last()
+                .map(String::as_str)
+                .into_iter()
+                .collect();
+        }
+        let active = |accept: &dyn Fn(u64) -> bool| {
+            self.fork_epochs
+                .iter()
+                .rposition(|fork_epoch| accept(*fork_epoch))
+                .map(|index| self.fork_versions[index].as_str())
+        };
+        let scheduled = active(&|fork_epoch| fork_epoch <= epoch);
+        let previous =
+            active(&|fork_epoch| fork_epoch < epoch).filter(|_| self.fork_epochs.contains(&epoch));
+        scheduled.into_iter().chain(previous).collect()
+    }
+
+    pub fn is_current_fork_version(&self, fork_version: &str, epoch: u64) -> bool {
+        self.fork_versions.is_empty()
+            || self
+                .fork_versions_at(epoch)
+                .iter()
+                .any(|current| current.eq_ignore_ascii_case(fork_version))
+    }
+}
+
+pub fn builtin_networks() -> HashMap<String, Network> {
+    HashMap::from([
+        (
+            "mainnet".to_string(),
+            Network::ethereum(
+                "0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95",
+                1606824023,
+                &[
+                    ("0x00000000", 0),
+                    ("0x01000000", 74240),
+                    ("0x02000000", 144896),
+                    ("0x03000000", 194048),
+                    ("0x04000000", 269568),
+                    ("0x05000000", 364032),
+                    ("0x06000000", 411392),
+                ],
+            ),
+        ),
+        (
+            "goerli".to_string(),
+            Network::ethereum(
+                "0x043db0d9a83813551ee2f33450d23797757d430911a9320530ad8a0eabc43efb",
+                1616508000,
+                &[
+                    ("0x00001020", 0),
+                    ("0x01001020", 36660),
+                    ("0x02001020", 112260),
+                    ("0x03001020", 162304),
+                    ("0x04001020", 231680),
+                ],
+            ),
+        ),
+        (
+            "sepolia".to_string(),
+            Network::ethereum(
+                "0xd8ea171f3c94aea21ebc42a1ed61052acf3f9209c00e4efbaaddac09ed9b8078",
+                1655733600,
+                &[
+                    ("0x90000069", 0),
+                    ("0x90000070", 50),
+                    ("0x90000071", 100),
+                    ("0x90000072", 56832),
+                    ("0x90000073", 132608),
+                    ("0x90000074", 222464),
+                    ("0x90000075", 272640),
+                ],
+            ),
+        ),
+        (
+            "holesky".to_string(),
+            Network::ethereum(
+                "0x9143aa7c615a7f7115e2b6aac319c03529df8242ae705fba9df39b79c59fa8b1",
+                1695902400,
+                &[
+                    ("0x01017000", 0),
+                    ("0x02017000", 0),
+                    ("0x03017000", 0),
+                    ("0x04017000", 256),
+                    ("0x05017000", 29696),
+                    ("0x06017000", 115968),
+                    ("0x07017000", 165120),
+                ],
+            ),
+        ),
+        (
+            "hoodi".to_string(),
+            Network::ethereum(
+                "0x212f13fc4df078b6cb7db228f1c8307566dcecf900867401a92023d7ba99cb5f",
+                1742213400,
+                &[
+                    ("0x10000910", 0),
+                    ("0x20000910", 0),
+                    ("0x30000910", 0),
+                    ("0x40000910", 0),
+                    ("0x50000910", 0),
+                    ("0x60000910", 2048),
+                    ("0x70000910", 50688),
+                ],
+            ),
+        ),
+        (
+            "gnosis".to_string(),
+            Network {
+                genesis_validators_root:
+                    "0xf5dcb5564e829aab27264b9becd5dfaa017085611224cb3036f573368dbb9d47".to_string(),
+                genesis_time: 1638993340,
+                seconds_per_slot: 5,
+                slots_per_epoch: 16,
+                fork_versions: [
+                    "0x00000064",
+                    "0x01000064",
+                    "0x02000064",
+                    "0x03000064",
+                    "0x04000064",
+                    "0x05000064",
+                ]
+                .iter()
+                .map(|v| v.to_string())
+                .collect(),
+                fork_epochs: vec![0, 512, 385536, 648704, 889856, 1337856],
+            },
+        ),
+    ])
+}
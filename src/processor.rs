@@ -1,43 +1,322 @@
-use crate::client::{ResponsePayloadWithEndpointInfo, SuccessEndpointPayload};
+use crate::client::{
+    BeaconBlockHeader, BlockInfo, Data, HeaderPayload, ResponsePayloadWithEndpointInfo,
+    SuccessEndpointPayload, SyncIssue,
+};
 use crate::errors::AppError;
+use crate::quorum::QuorumPolicy;
 use colored::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::Formatter;
+use std::str::FromStr;
+use strum_macros::Display;
+
+// providers only agree when they return the same root for the same epoch
+type CheckpointKey = (String, String);
+
+#[derive(
+    Display,
+    Debug,
+    Copy,
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum CheckpointKind {
+    #[default]
+    Finalized,
+    CurrentJustified,
+    PreviousJustified,
+}
+
+impl CheckpointKind {
+    pub const ALL: [CheckpointKind; 3] = [
+        CheckpointKind::Finalized,
+        CheckpointKind::CurrentJustified,
+        CheckpointKind::PreviousJustified,
+    ];
+
+    pub fn block_info<'a>(&self, data: &'a Data) -> &'a BlockInfo {
+        match self {
+            CheckpointKind::Finalized => &data.finalized,
+            CheckpointKind::CurrentJustified => &data.current_justified,
+            CheckpointKind::PreviousJustified => &data.previous_justified,
+        }
+    }
+}
+
+#[derive(Display, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum QuorumStatus {
+    // a single checkpoint reached quorum
+    Canonical,
+    // providers returned different checkpoints and none reached quorum
+    Conflicting,
+    // providers agree, but not enough of them responded to reach quorum
+    Insufficient,
+    // no provider returned a checkpoint
+    Failed,
+    // a checkpoint reached quorum, but the providers that agreed on it returned different block
+    // headers for it, or a header outside of its epoch
+    HeaderMismatch,
+}
 
 #[derive(Debug)]
 struct GroupedResult {
-    success: HashMap<String, Vec<SuccessPayload>>,
+    success: HashMap<CheckpointKey, Vec<SuccessPayload>>,
     failure: Vec<FailurePayload>,
+    not_awaited: Vec<String>,
+    flagged: Vec<FlaggedEndpoint>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DisplayableResult {
+    pub checkpoint: CheckpointKind,
     pub canonical: Option<HashMap<String, Vec<SuccessPayload>>>,
     pub non_canonical: Option<HashMap<String, Vec<SuccessPayload>>>,
+    pub epoch_mismatch: Option<HashMap<String, Vec<SuccessPayload>>>,
     pub failure: Vec<FailurePayload>,
+    pub not_awaited: Vec<String>,
+    // endpoints whose vote was counted although they are not in sync
+    pub flagged: Vec<FlaggedEndpoint>,
+    pub quorum_policy: QuorumPolicy,
+    pub threshold: u64,
+    // weight of every endpoint queried, an operator counting once, as the quorum policy sees it
+    pub configured_weight: u64,
+    pub min_distinct_operators: u64,
+    pub tally: Vec<CheckpointTally>,
+    // the header of the block that reached quorum, as returned by the providers that agreed on it
+    pub header: Option<HeaderVerification>,
+}
+
+impl DisplayableResult {
+    pub fn status(&self) -> QuorumStatus {
+        let header_mismatch = self
+            .header
+            .as_ref()
+            .is_some_and(|header| header.mismatch.is_some());
+        if self.canonical.is_some() && header_mismatch {
+            QuorumStatus::HeaderMismatch
+        } else if self.canonical.is_some() {
+            QuorumStatus::Canonical
+        } else if self.tally.len() > 1 {
+            QuorumStatus::Conflicting
+        } else if self.tally.len() == 1 {
+            QuorumStatus::Insufficient
+        } else {
+            QuorumStatus::Failed
+        }
+    }
+
+    // the header every provider that agreed on the checkpoint returned
+    pub fn verified_header(&self) -> Option<&BeaconBlockHeader> {
+        self.header
+            .as_ref()
+            .and_then(|header| header.header.as_ref())
+    }
+
+    // the (block root, epoch) that reached quorum
+    pub fn canonical_checkpoint(&self) -> Option<&BlockInfo> {
+        self.canonical
+            .as_ref()
+            .and_then(|canonical| canonical.values().next())
+            .and_then(|values| values.first())
+            .map(|value| self.checkpoint.block_info(&value.payload.data))
+    }
+
+    pub fn verdict(&self) -> Verdict {
+        let status = self.status();
+        let (block_root, epoch) = match self.canonical_checkpoint() {
+            Some(block_info) if status == QuorumStatus::Canonical => (
+                Some(block_info.root.clone()),
+                Some(block_info.epoch.clone()),
+            ),
+            _ => (None, None),
+        };
+        let (slot, state_root) = match self.verified_header() {
+            Some(header) => (Some(header.slot.clone()), Some(header.state_root.clone())),
+            None => (None, None),
+        };
+        Verdict {
+            block_root,
+            epoch,
+            slot,
+            state_root,
+            status,
+        }
+    }
+}
+
+// The outcome of the quorum analysis, the block root and epoch are only set for a canonical
+// checkpoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verdict {
+    pub block_root: Option<String>,
+    pub epoch: Option<String>,
+    pub slot: Option<String>,
+    pub state_root: Option<String>,
+    pub status: QuorumStatus,
+}
+
+// The verdict along with the full result it was reached from, as printed by the json and yaml
+// outputs
+#[derive(Debug, Serialize)]
+pub struct Report {
+    #[serde(flatten)]
+    pub verdict: Verdict,
+    pub result: DisplayableResult,
+}
+
+impl From<DisplayableResult> for Report {
+    fn from(result: DisplayableResult) -> Self {
+        Report {
+            verdict: result.verdict(),
+            result,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum OutputFormat {
+    // coloured text for humans
+    #[default]
+    Text,
+    // the verdict, then the weighted tally, in aligned columns
+    Table,
+    // one key=value line per field of the verdict
+    Plain,
+    Json,
+    Yaml,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Table => write!(f, "table"),
+            OutputFormat::Plain => write!(f, "plain"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Yaml => write!(f, "yaml"),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "table" => Ok(OutputFormat::Table),
+            "plain" => Ok(OutputFormat::Plain),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            other => Err(AppError::InvalidOutputFormat {
+                message: format!(
+                    "Unknown output format {other}. Expected one of json, yaml, text, table or plain"
+                ),
+            }),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl TryFrom<String> for OutputFormat {
+    type Error = AppError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<OutputFormat> for String {
+    fn from(value: OutputFormat) -> Self {
+        value.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CheckpointTally {
+    pub root: String,
+    pub epoch: String,
+    pub weight: u64,
+    pub distinct_operators: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SuccessPayload {
     pub payload: SuccessEndpointPayload,
     pub endpoint: String,
+    pub weight: u64,
+    pub operator: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeaderVerification {
+    // the header returned by every provider that returned one, unset when they differ
+    pub header: Option<BeaconBlockHeader>,
+    // why the headers returned do not match the checkpoint
+    pub mismatch: Option<String>,
+    pub headers: Vec<EndpointHeader>,
+    pub failure: Vec<FailurePayload>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointHeader {
+    pub endpoint: String,
+    pub header: BeaconBlockHeader,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FlaggedEndpoint {
+    pub endpoint: String,
+    pub issues: Vec<SyncIssue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FailurePayload {
     pub payload: AppError,
     pub endpoint: String,
 }
 
-fn group_success_failure(response_payload: Vec<ResponsePayloadWithEndpointInfo>) -> GroupedResult {
-    let (successes, failures): (Vec<SuccessPayload>, Vec<FailurePayload>) = response_payload
+fn group_success_failure(
+    response_payload: Vec<ResponsePayloadWithEndpointInfo>,
+    checkpoint: CheckpointKind,
+) -> GroupedResult {
+    let flagged: Vec<FlaggedEndpoint> = response_payload
+        .iter()
+        .filter(|result| result.payload.is_ok() && !result.sync_issues.is_empty())
+        .map(|result| FlaggedEndpoint {
+            endpoint: result.endpoint.clone(),
+            issues: result.sync_issues.clone(),
+        })
+        .collect();
+    let (successes, failures, not_awaited): (
+        Vec<SuccessPayload>,
+        Vec<FailurePayload>,
+        Vec<String>,
+    ) = response_payload
         .into_iter()
-        .fold((vec![], vec![]), |mut acc, result| {
+        .fold((vec![], vec![], vec![]), |mut acc, result| {
             match result.payload {
                 Ok(success) => acc.0.push(SuccessPayload {
                     payload: success,
                     endpoint: result.endpoint,
+                    weight: result.weight,
+                    operator: result.operator,
                 }),
+                // endpoints dropped once quorum was decided are neither a success nor a failure
+                Err(AppError::NotAwaited { .. }) => acc.2.push(result.endpoint),
                 Err(error) => acc.1.push(FailurePayload {
                     payload: error,
                     endpoint: result.endpoint,
@@ -46,10 +325,11 @@ fn group_success_failure(response_payload: Vec<ResponsePayloadWithEndpointInfo>)
             acc
         });
 
-    let mut hash_to_successes: HashMap<String, Vec<SuccessPayload>> = HashMap::new();
+    let mut hash_to_successes: HashMap<CheckpointKey, Vec<SuccessPayload>> = HashMap::new();
     successes.into_iter().for_each(|entry| {
+        let block_info = checkpoint.block_info(&entry.payload.data);
         hash_to_successes
-            .entry(entry.payload.data.finalized.root.clone())
+            .entry((block_info.root.clone(), block_info.epoch.clone()))
             .or_default()
             .push(entry)
     });
@@ -57,59 +337,325 @@ fn group_success_failure(response_payload: Vec<ResponsePayloadWithEndpointInfo>)
     GroupedResult {
         success: hash_to_successes,
         failure: failures,
+        not_awaited,
+        flagged,
     }
 }
 
 pub fn process_to_displayable_format(
     response_payload: Vec<ResponsePayloadWithEndpointInfo>,
+    checkpoint: CheckpointKind,
+    quorum_policy: QuorumPolicy,
+    min_distinct_operators: u64,
 ) -> DisplayableResult {
+    let configured = operator_weight(
+        response_payload
+            .iter()
+            .map(|result| (result.operator.as_str(), result.weight)),
+    );
     // groups the results into
     // failures
     // success
-    //   - success grouped by their block_root hash
-    let grouped_result = group_success_failure(response_payload);
+    //   - success grouped by their (block_root hash, epoch) pair
+    let grouped_result = group_success_failure(response_payload, checkpoint);
+    let mut tally: Vec<CheckpointTally> = grouped_result
+        .success
+        .iter()
+        .map(|((root, epoch), values)| CheckpointTally {
+            root: root.clone(),
+            epoch: epoch.clone(),
+            weight: weighted_tally(values),
+            distinct_operators: operator_count(values),
+        })
+        .collect();
+    tally.sort_by(|a, b| b.weight.cmp(&a.weight).then(a.root.cmp(&b.root)));
+    let responders = operator_weight(
+        grouped_result
+            .success
+            .values()
+            .flatten()
+            .map(|value| (value.operator.as_str(), value.weight)),
+    );
+    let threshold = quorum_policy.threshold(configured, responders);
+
+    // the same root reported with different epochs is a conflict of its own
+    let mut epochs_per_root: HashMap<String, Vec<SuccessPayload>> = HashMap::new();
+    grouped_result
+        .success
+        .iter()
+        .for_each(|((root, _), values)| {
+            epochs_per_root
+                .entry(root.clone())
+                .or_default()
+                .extend(values.iter().cloned())
+        });
+    let mismatched: HashMap<String, Vec<SuccessPayload>> = epochs_per_root
+        .into_iter()
+        .filter(|(_, values)| {
+            values
+                .iter()
+                .map(|value| checkpoint.block_info(&value.payload.data).epoch.as_str())
+                .collect::<HashSet<&str>>()
+                .len()
+                > 1
+        })
+        .collect();
+
     let mut canonical: Option<HashMap<String, Vec<SuccessPayload>>> = None;
     let mut non_canonical: Option<HashMap<String, Vec<SuccessPayload>>> = None;
 
     if !grouped_result.success.is_empty() {
-        if grouped_result.success.keys().len() == 1 {
-            canonical = Some(grouped_result.success);
+        let (passed_threshold, below_threshold): (
+            HashMap<CheckpointKey, Vec<SuccessPayload>>,
+            HashMap<CheckpointKey, Vec<SuccessPayload>>,
+        ) = grouped_result.success.into_iter().partition(|(_, values)| {
+            weighted_tally(values) >= threshold && operator_count(values) >= min_distinct_operators
+        });
+        if passed_threshold.keys().len() == 1 {
+            // if there is only one value they passed the threshold that is the canonical result
+            canonical = Some(
+                passed_threshold
+                    .into_iter()
+                    .map(|((root, _), values)| (root, values))
+                    .collect(),
+            )
         } else {
-            // more than one results, pick one with values more than 2/3
-            let total_value = grouped_result.success.values().len() as f64;
-            let threshold = (2f64 / 3f64 * total_value).floor();
-            let (passed_threshold, below_threshold): (
-                HashMap<String, Vec<SuccessPayload>>,
-                HashMap<String, Vec<SuccessPayload>>,
-            ) = grouped_result
-                .success
+            // else the non_canonical will include
+            // the multiple values that passed the threshold
+            // the values that did not even pass the threshold
+            // roots with mismatched epochs are only reported under epoch_mismatch
+            let conflicting: HashMap<String, Vec<SuccessPayload>> = passed_threshold
                 .into_iter()
-                .partition(|(_, values)| values.len() as f64 > threshold);
-            if passed_threshold.keys().len() == 1 {
-                // if there is only one value they passed the threshold that is the canonical result
-                canonical = Some(passed_threshold)
-            } else {
-                // else the non_canonical will include
-                // the multiple values that passed the threshold
-                // the values that did not even pass the threshold
-                non_canonical = Some(
-                    passed_threshold
-                        .into_iter()
-                        .chain(below_threshold)
-                        .collect(),
-                )
+                .chain(below_threshold)
+                .filter(|((root, _), _)| !mismatched.contains_key(root))
+                .map(|((root, _), values)| (root, values))
+                .collect();
+            if !conflicting.is_empty() {
+                non_canonical = Some(conflicting)
             }
         }
     };
 
     DisplayableResult {
+        checkpoint,
         canonical,
         non_canonical,
+        epoch_mismatch: if mismatched.is_empty() {
+            None
+        } else {
+            Some(mismatched)
+        },
         failure: grouped_result.failure,
+        not_awaited: grouped_result.not_awaited,
+        flagged: grouped_result.flagged,
+        quorum_policy,
+        threshold,
+        configured_weight: configured,
+        min_distinct_operators,
+        tally,
+        header: None,
+    }
+}
+
+// Checks that the headers returned for the block of a checkpoint agree on the slot, parent root,
+// state root and body root, and that the slot is the start slot of the epoch of the checkpoint,
+// or the slot of the last block before it when that slot is empty. Providers that did not return
+// a header are reported as failures, without failing the verification.
+pub fn verify_headers(
+    checkpoint: &BlockInfo,
+    slots_per_epoch: u64,
+    responses: Vec<(String, Result<HeaderPayload, AppError>)>,
+) -> HeaderVerification {
+    let mut headers = vec![];
+    let mut failure = vec![];
+    for (endpoint, response) in responses {
+        match response {
+            Ok(payload) if payload.data.root != checkpoint.root => failure.push(FailurePayload {
+                payload: AppError::InvalidPayload {
+                    endpoint: endpoint.clone(),
+                    message: format!(
+                        "Header of block {} returned instead of {}",
+                        payload.data.root, checkpoint.root
+                    ),
+                },
+                endpoint,
+            }),
+            Ok(payload) => headers.push(EndpointHeader {
+                endpoint,
+                header: payload.data.header.message,
+            }),
+            Err(error) => failure.push(FailurePayload {
+                payload: error,
+                endpoint,
+            }),
+        }
+    }
+
+    let key = |header: &BeaconBlockHeader| {
+        (
+            header.slot.clone(),
+            header.parent_root.clone(),
+            header.state_root.clone(),
+            header.body_root.clone(),
+        )
+    };
+    let distinct = headers
+        .iter()
+        .map(|value| key(&value.header))
+        .collect::<HashSet<_>>()
+        .len();
+    let (header, mismatch) = match headers.first() {
+        None => (None, None),
+        Some(_) if distinct > 1 => (
+            None,
+            Some(format!(
+                "Providers returned {distinct} different headers for block {}",
+                checkpoint.root
+            )),
+        ),
+        Some(value) => {
+            let header = value.header.clone();
+            // the epoch comes from the providers, so its first slot may not fit in a u64
+            let epoch_start = checkpoint.epoch.parse::<u64>().ok().and_then(|epoch| {
+                epoch
+                    .checked_mul(slots_per_epoch)
+                    .map(|start_slot| (epoch, start_slot))
+            });
+            let mismatch = match (header.slot.parse::<u64>(), epoch_start) {
+                (Ok(slot), Some((epoch, start_slot))) => {
+                    let within_epoch =
+                        slot <= start_slot && (epoch == 0 || slot > start_slot - slots_per_epoch);
+                    (!within_epoch).then(|| {
+                        format!(
+                            "Slot {slot} of block {} is not within epoch {epoch}",
+                            checkpoint.root
+                        )
+                    })
+                }
+                _ => Some(format!(
+                    "Invalid slot {} or epoch {}",
+                    header.slot, checkpoint.epoch
+                )),
+            };
+            (Some(header), mismatch)
+        }
+    };
+
+    HeaderVerification {
+        header: if mismatch.is_none() { header } else { None },
+        mismatch,
+        headers,
+        failure,
     }
 }
 
+// Whether the outcome of a quorum round can no longer change, whatever the pending endpoints,
+// given as (operator, weight), end up returning. This is the case when a single checkpoint has
+// reached the highest threshold possible and no other can catch up, or when no checkpoint can
+// reach the lowest threshold possible anymore.
+pub fn is_quorum_decided(
+    received: &[&ResponsePayloadWithEndpointInfo],
+    pending: &[(&str, u64)],
+    checkpoint: CheckpointKind,
+    quorum_policy: QuorumPolicy,
+    min_distinct_operators: u64,
+) -> bool {
+    if pending.is_empty() {
+        return true;
+    }
+    let configured = operator_weight(
+        received
+            .iter()
+            .map(|result| (result.operator.as_str(), result.weight))
+            .chain(pending.iter().copied()),
+    );
+    let remaining = operator_weight(pending.iter().copied());
+
+    let mut votes: HashMap<(&str, &str), Vec<(&str, u64)>> = HashMap::new();
+    received.iter().for_each(|result| {
+        if let Ok(success) = &result.payload {
+            let block_info = checkpoint.block_info(&success.data);
+            votes
+                .entry((block_info.root.as_str(), block_info.epoch.as_str()))
+                .or_default()
+                .push((result.operator.as_str(), result.weight))
+        }
+    });
+    let responders = operator_weight(votes.values().flatten().copied());
+    let lowest_threshold = quorum_policy.threshold(configured, responders);
+    let highest_threshold = quorum_policy.threshold(configured, responders + remaining);
+
+    let mut tallies: Vec<(u64, u64)> = votes
+        .values()
+        .map(|values| {
+            let operators = values
+                .iter()
+                .map(|(operator, _)| *operator)
+                .collect::<HashSet<&str>>()
+                .len() as u64;
+            (operator_weight(values.iter().copied()), operators)
+        })
+        .collect();
+    tallies.sort_by_key(|tally| std::cmp::Reverse(tally.0));
+    let (leader, leader_operators) = tallies.first().copied().unwrap_or((0, 0));
+    let runner_up = tallies.get(1).map(|tally| tally.0).unwrap_or(0);
+
+    let canonical_decided = leader >= highest_threshold
+        && leader_operators >= min_distinct_operators
+        && runner_up + remaining < lowest_threshold;
+    let impossible = leader + remaining < lowest_threshold;
+    canonical_decided || impossible
+}
+
+// an operator votes at most once, with the highest weight among its endpoints
+pub fn operator_weight<'a>(votes: impl Iterator<Item = (&'a str, u64)>) -> u64 {
+    let mut per_operator: HashMap<&str, u64> = HashMap::new();
+    votes.for_each(|(operator, weight)| {
+        let entry = per_operator.entry(operator).or_default();
+        *entry = (*entry).max(weight);
+    });
+    per_operator.values().sum()
+}
+
+fn weighted_tally(values: &[SuccessPayload]) -> u64 {
+    operator_weight(
+        values
+            .iter()
+            .map(|value| (value.operator.as_str(), value.weight)),
+    )
+}
+
+fn operator_count(values: &[SuccessPayload]) -> u64 {
+    values
+        .iter()
+        .map(|value| value.operator.as_str())
+        .collect::<HashSet<&str>>()
+        .len() as u64
+}
+
 pub fn print_result(result: DisplayableResult, is_verbose: bool) {
+    if is_verbose {
+        println!(
+            "{}: \t{} (threshold {})",
+            "Quorum".blue(),
+            result.quorum_policy,
+            result.threshold
+        );
+        println!(
+            "{}: \t{}",
+            "Min distinct operators".blue(),
+            result.min_distinct_operators
+        );
+        println!("{}:", "Weighted tally".blue());
+        for tally in &result.tally {
+            println!(
+                "\t {} (epoch {}): {} ({} operators)",
+                tally.root, tally.epoch, tally.weight, tally.distinct_operators
+            );
+        }
+    }
+
+    let canonical_checkpoint = result.canonical_checkpoint().cloned();
     if let Some(canonical_result) = result.canonical {
         println!(
             "{}: {}",
@@ -122,14 +668,20 @@ pub fn print_result(result: DisplayableResult, is_verbose: bool) {
                 .bold()
         );
 
-        if let Some((_, first_value)) = canonical_result.iter().next() {
-            if let Some(first_item) = first_value.iter().next() {
-                println!(
-                    "{}: \t{}",
-                    "Epoch".blue(),
-                    first_item.payload.data.finalized.epoch.green().bold()
-                );
-            }
+        if let Some(block_info) = canonical_checkpoint {
+            println!("{}: \t{}", "Epoch".blue(), block_info.epoch.green().bold());
+        }
+        if let Some(header) = result
+            .header
+            .as_ref()
+            .and_then(|header| header.header.as_ref())
+        {
+            println!("{}: \t{}", "Slot".blue(), header.slot.green().bold());
+            println!(
+                "{}: {}",
+                "State root".blue(),
+                header.state_root.green().bold()
+            );
         }
 
         if is_verbose {
@@ -163,6 +715,65 @@ pub fn print_result(result: DisplayableResult, is_verbose: bool) {
         }
     }
 
+    if let Some(epoch_mismatch_result) = result.epoch_mismatch {
+        println!("{}", "Epoch mismatch:".yellow().bold());
+        if is_verbose {
+            println!(
+                "{}:\n \t{}",
+                "Details".yellow(),
+                serde_json::to_string_pretty(&epoch_mismatch_result)
+                    .unwrap_or("displaying verbose failed".to_string())
+                    .yellow()
+            );
+        } else {
+            for (key, values) in &epoch_mismatch_result {
+                println!("\t Checkpoint: {}", key.yellow());
+                for value in values {
+                    println!(
+                        "\t\t {} (epoch {})",
+                        value.endpoint.yellow(),
+                        result
+                            .checkpoint
+                            .block_info(&value.payload.data)
+                            .epoch
+                            .yellow()
+                    );
+                }
+            }
+        }
+    }
+
+    if let Some(mismatch) = result
+        .header
+        .as_ref()
+        .and_then(|header| header.mismatch.as_ref())
+    {
+        println!("{}: {}", "Header mismatch".red().bold(), mismatch.red());
+    }
+
+    if !result.flagged.is_empty() {
+        println!("{}", "Not in sync:".yellow().bold());
+        for flagged in &result.flagged {
+            println!(
+                "\t Endpoint: {} ({})",
+                flagged.endpoint.yellow(),
+                flagged
+                    .issues
+                    .iter()
+                    .map(|issue| issue.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    if !result.not_awaited.is_empty() {
+        println!("{}", "Not awaited:".blue().bold());
+        for endpoint in &result.not_awaited {
+            println!("\t Endpoint: {}", endpoint);
+        }
+    }
+
     if !result.failure.is_empty() {
         println!("{}", "Errors:".red().bold());
         if is_verbose {
@@ -181,3 +792,145 @@ pub fn print_result(result: DisplayableResult, is_verbose: bool) {
         }
     }
 }
+
+fn plain_lines(verdict: &Verdict, prefix: &str) -> Vec<String> {
+    let mut lines = vec![format!("{prefix}status={}", verdict.status)];
+    let fields = [
+        ("block_root", &verdict.block_root),
+        ("epoch", &verdict.epoch),
+        ("slot", &verdict.slot),
+        ("state_root", &verdict.state_root),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value {
+            lines.push(format!("{prefix}{name}={value}"));
+        }
+    }
+    lines
+}
+
+// rows padded so that their columns line up, two spaces apart
+fn aligned(rows: Vec<Vec<String>>) -> Vec<String> {
+    let mut widths: Vec<usize> = vec![];
+    for row in &rows {
+        for (column, cell) in row.iter().enumerate() {
+            if column == widths.len() {
+                widths.push(0);
+            }
+            widths[column] = widths[column].max(cell.len());
+        }
+    }
+    rows.into_iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect::<Vec<String>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+// the verdict of each checkpoint, then a row per entry of their weighted tally
+pub fn table(results: &[DisplayableResult]) -> String {
+    let missing = || "-".to_string();
+    let mut verdicts = vec![[
+        "CHECKPOINT",
+        "STATUS",
+        "BLOCK ROOT",
+        "EPOCH",
+        "SLOT",
+        "STATE ROOT",
+    ]
+    .map(String::from)
+    .to_vec()];
+    let mut tallies = vec![["CHECKPOINT", "ROOT", "EPOCH", "WEIGHT", "OPERATORS"]
+        .map(String::from)
+        .to_vec()];
+    for result in results {
+        let verdict = result.verdict();
+        verdicts.push(vec![
+            result.checkpoint.to_string(),
+            verdict.status.to_string(),
+            verdict.block_root.unwrap_or_else(missing),
+            verdict.epoch.unwrap_or_else(missing),
+            verdict.slot.unwrap_or_else(missing),
+            verdict.state_root.unwrap_or_else(missing),
+        ]);
+        tallies.extend(result.tally.iter().map(|tally| {
+            vec![
+                result.checkpoint.to_string(),
+                tally.root.clone(),
+                tally.epoch.clone(),
+                tally.weight.to_string(),
+                tally.distinct_operators.to_string(),
+            ]
+        }));
+    }
+    let mut lines = aligned(verdicts);
+    lines.push(String::new());
+    lines.extend(aligned(tallies));
+    lines.join("\n")
+}
+
+fn print_serialized<T: Serialize>(value: &T, output: OutputFormat) {
+    let serialized = match output {
+        OutputFormat::Yaml => serde_yaml::to_string(value).map_err(|e| e.to_string()),
+        _ => serde_json::to_string_pretty(value).map_err(|e| e.to_string()),
+    };
+    match serialized {
+        Ok(serialized) => println!("{}", serialized.trim_end()),
+        Err(e) => eprintln!("displaying result failed: {e}"),
+    }
+}
+
+pub fn print_output(result: DisplayableResult, output: OutputFormat, is_verbose: bool) {
+    match output {
+        OutputFormat::Text => print_result(result, is_verbose),
+        OutputFormat::Table => println!("{}", table(&[result])),
+        OutputFormat::Plain => {
+            for line in plain_lines(&result.verdict(), "") {
+                println!("{line}");
+            }
+        }
+        OutputFormat::Json | OutputFormat::Yaml => print_serialized(&Report::from(result), output),
+    }
+}
+
+pub fn print_all_checkpoints_output(
+    results: Vec<DisplayableResult>,
+    output: OutputFormat,
+    is_verbose: bool,
+) {
+    match output {
+        OutputFormat::Text => print_all_checkpoints_result(results, is_verbose),
+        OutputFormat::Table => println!("{}", table(&results)),
+        // the fields of each checkpoint are prefixed with its kind, as in finalized.status
+        OutputFormat::Plain => {
+            for result in results {
+                let prefix = format!("{}.", result.checkpoint);
+                for line in plain_lines(&result.verdict(), &prefix) {
+                    println!("{line}");
+                }
+            }
+        }
+        OutputFormat::Json | OutputFormat::Yaml => {
+            let reports: Vec<Report> = results.into_iter().map(Report::from).collect();
+            print_serialized(&reports, output)
+        }
+    }
+}
+
+pub fn print_all_checkpoints_result(results: Vec<DisplayableResult>, is_verbose: bool) {
+    for result in results {
+        println!(
+            "{} {}",
+            "Checkpoint:".blue().bold(),
+            result.checkpoint.to_string().bold()
+        );
+        print_result(result, is_verbose);
+        println!();
+    }
+}
@@ -0,0 +1,144 @@
+use crate::errors::AppError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+// The policy used to decide whether a block root has enough votes to be canonical. Each endpoint
+// votes with the weight configured for it, which defaults to 1.
+//
+// Textual form, used by the CLI, the endpoints config and the server query string:
+//   endpoints:<n>/<d>  - more than n/d of the configured endpoints
+//   responders:<n>/<d> - more than n/d of the endpoints that returned a checkpoint
+//   min:<count>        - at least <count> votes
+//   unanimous          - every configured endpoint
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum QuorumPolicy {
+    EndpointFraction { numerator: u64, denominator: u64 },
+    ResponderFraction { numerator: u64, denominator: u64 },
+    MinCount(u64),
+    Unanimous,
+}
+
+impl Default for QuorumPolicy {
+    fn default() -> Self {
+        QuorumPolicy::EndpointFraction {
+            numerator: 2,
+            denominator: 3,
+        }
+    }
+}
+
+impl QuorumPolicy {
+    // Minimum weight of agreeing votes a block root needs to be canonical.
+    pub fn threshold(&self, configured: u64, responders: u64) -> u64 {
+        match self {
+            QuorumPolicy::EndpointFraction {
+                numerator,
+                denominator,
+            } => more_than_fraction(configured, *numerator, *denominator),
+            QuorumPolicy::ResponderFraction {
+                numerator,
+                denominator,
+            } => more_than_fraction(responders, *numerator, *denominator),
+            QuorumPolicy::MinCount(count) => *count,
+            QuorumPolicy::Unanimous => configured.max(1),
+        }
+    }
+}
+
+// computed in u128 as the configured weights can be large enough for the product to overflow
+fn more_than_fraction(total: u64, numerator: u64, denominator: u64) -> u64 {
+    let share = u128::from(total) * u128::from(numerator) / u128::from(denominator);
+    u64::try_from(share).unwrap_or(u64::MAX).saturating_add(1)
+}
+
+fn parse_fraction(input: &str) -> Result<(u64, u64), AppError> {
+    let invalid = || AppError::InvalidQuorumPolicy {
+        message: format!("Invalid fraction {input}"),
+    };
+    let (numerator, denominator) = input.split_once('/').ok_or_else(invalid)?;
+    let numerator: u64 = numerator.trim().parse().map_err(|_| invalid())?;
+    let denominator: u64 = denominator.trim().parse().map_err(|_| invalid())?;
+    // more than the whole can never be reached, unanimous is the policy requiring every endpoint
+    if numerator == 0 || denominator == 0 || numerator >= denominator {
+        return Err(AppError::InvalidQuorumPolicy {
+            message: format!(
+                "Fraction {input} must be greater than 0 and less than 1, use unanimous to require every endpoint"
+            ),
+        });
+    }
+    Ok((numerator, denominator))
+}
+
+impl FromStr for QuorumPolicy {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let (kind, value) = match s.split_once(':') {
+            Some((kind, value)) => (kind, Some(value)),
+            None => (s.as_str(), None),
+        };
+        match (kind, value) {
+            ("endpoints", Some(value)) => {
+                let (numerator, denominator) = parse_fraction(value)?;
+                Ok(QuorumPolicy::EndpointFraction {
+                    numerator,
+                    denominator,
+                })
+            }
+            ("responders", Some(value)) => {
+                let (numerator, denominator) = parse_fraction(value)?;
+                Ok(QuorumPolicy::ResponderFraction {
+                    numerator,
+                    denominator,
+                })
+            }
+            ("min", Some(value)) => match value.trim().parse::<u64>() {
+                Ok(count) if count > 0 => Ok(QuorumPolicy::MinCount(count)),
+                _ => Err(AppError::InvalidQuorumPolicy {
+                    message: format!("Minimum count must be a positive number, got {value}"),
+                }),
+            },
+            ("unanimous", None) => Ok(QuorumPolicy::Unanimous),
+            _ => Err(AppError::InvalidQuorumPolicy {
+                message: format!(
+                    "Unknown quorum policy {s}. Expected one of endpoints:<n>/<d>, responders:<n>/<d>, min:<count> or unanimous"
+                ),
+            }),
+        }
+    }
+}
+
+impl TryFrom<String> for QuorumPolicy {
+    type Error = AppError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<QuorumPolicy> for String {
+    fn from(value: QuorumPolicy) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for QuorumPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            QuorumPolicy::EndpointFraction {
+                numerator,
+                denominator,
+            } => write!(f, "endpoints:{numerator}/{denominator}"),
+            QuorumPolicy::ResponderFraction {
+                numerator,
+                denominator,
+            } => write!(f, "responders:{numerator}/{denominator}"),
+            QuorumPolicy::MinCount(count) => write!(f, "min:{count}"),
+            QuorumPolicy::Unanimous => write!(f, "unanimous"),
+        }
+    }
+}
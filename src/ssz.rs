@@ -0,0 +1,332 @@
+use crate::errors::AppError;
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+const BYTES_PER_CHUNK: usize = 32;
+const BYTES_PER_LENGTH_OFFSET: usize = 4;
+
+pub type Root = [u8; 32];
+
+// Description of an SSZ type, enough to walk its serialization and compute its hash_tree_root
+// without decoding it into rust types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SszType {
+    // unsigned integer of the given number of bytes, booleans are uint8
+    Uint(usize),
+    // ByteVector[n]
+    Bytes(usize),
+    // ByteList[limit]
+    ByteList(usize),
+    Vector(Box<SszType>, usize),
+    List(Box<SszType>, usize),
+    Bitvector(usize),
+    Bitlist(usize),
+    Container(Vec<(&'static str, SszType)>),
+}
+
+impl SszType {
+    pub fn vector(element: SszType, length: usize) -> Self {
+        SszType::Vector(Box::new(element), length)
+    }
+
+    pub fn list(element: SszType, limit: usize) -> Self {
+        SszType::List(Box::new(element), limit)
+    }
+
+    // size of the serialization, None for variable size types
+    pub fn fixed_size(&self) -> Option<usize> {
+        match self {
+            SszType::Uint(size) | SszType::Bytes(size) => Some(*size),
+            SszType::Bitvector(length) => Some(length.div_ceil(8)),
+            SszType::Vector(element, length) => element.fixed_size().map(|size| size * length),
+            SszType::Container(fields) => fields.iter().map(|(_, field)| field.fixed_size()).sum(),
+            SszType::ByteList(_) | SszType::List(_, _) | SszType::Bitlist(_) => None,
+        }
+    }
+
+    fn is_basic(&self) -> bool {
+        matches!(self, SszType::Uint(_))
+    }
+
+    // the serialization of a field of a container
+    pub fn field<'a>(&self, bytes: &'a [u8], name: &str) -> Result<&'a [u8], AppError> {
+        let SszType::Container(fields) = self else {
+            return Err(invalid(format!(
+                "Field {name} requested from a non container type"
+            )));
+        };
+        let index = fields
+            .iter()
+            .position(|(field, _)| *field == name)
+            .ok_or_else(|| invalid(format!("Unknown field {name}")))?;
+        let types: Vec<&SszType> = fields.iter().map(|(_, field)| field).collect();
+        Ok(split_fields(&types, bytes)?[index])
+    }
+
+    pub fn hash_tree_root(&self, bytes: &[u8]) -> Result<Root, AppError> {
+        match self {
+            SszType::Uint(size) | SszType::Bytes(size) => {
+                check_length(bytes, *size)?;
+                Ok(merkleize(&pack(bytes), chunk_count(*size)))
+            }
+            SszType::ByteList(limit) => {
+                if bytes.len() > *limit {
+                    return Err(invalid(format!(
+                        "{} bytes exceed the limit of {limit}",
+                        bytes.len()
+                    )));
+                }
+                Ok(mix_in_length(
+                    merkleize(&pack(bytes), chunk_count(*limit)),
+                    bytes.len(),
+                ))
+            }
+            SszType::Bitvector(length) => {
+                check_length(bytes, length.div_ceil(8))?;
+                Ok(merkleize(&pack(bytes), chunk_count(length.div_ceil(8))))
+            }
+            SszType::Bitlist(limit) => {
+                let (bits, length) = bitlist_bits(bytes)?;
+                if length > *limit {
+                    return Err(invalid(format!(
+                        "{length} bits exceed the limit of {limit}"
+                    )));
+                }
+                Ok(mix_in_length(
+                    merkleize(&pack(&bits), chunk_count(limit.div_ceil(8))),
+                    length,
+                ))
+            }
+            SszType::Vector(element, length) => {
+                let elements = split_elements(element, bytes)?;
+                if elements.len() != *length {
+                    return Err(invalid(format!(
+                        "Vector of {} elements instead of {length}",
+                        elements.len()
+                    )));
+                }
+                elements_root(element, &elements, bytes, *length)
+            }
+            SszType::List(element, limit) => {
+                let elements = split_elements(element, bytes)?;
+                if elements.len() > *limit {
+                    return Err(invalid(format!(
+                        "List of {} elements exceeds the limit of {limit}",
+                        elements.len()
+                    )));
+                }
+                Ok(mix_in_length(
+                    elements_root(element, &elements, bytes, *limit)?,
+                    elements.len(),
+                ))
+            }
+            SszType::Container(fields) => {
+                let types: Vec<&SszType> = fields.iter().map(|(_, field)| field).collect();
+                let roots = split_fields(&types, bytes)?
+                    .into_iter()
+                    .zip(types)
+                    .map(|(field_bytes, field)| field.hash_tree_root(field_bytes))
+                    .collect::<Result<Vec<Root>, AppError>>()?;
+                Ok(merkleize(&roots, roots.len()))
+            }
+        }
+    }
+}
+
+fn invalid(message: String) -> AppError {
+    AppError::InvalidSsz { message }
+}
+
+fn check_length(bytes: &[u8], expected: usize) -> Result<(), AppError> {
+    if bytes.len() == expected {
+        Ok(())
+    } else {
+        Err(invalid(format!(
+            "Expected {expected} bytes, got {}",
+            bytes.len()
+        )))
+    }
+}
+
+fn chunk_count(bytes: usize) -> usize {
+    bytes.div_ceil(BYTES_PER_CHUNK)
+}
+
+fn read_offset(bytes: &[u8], position: usize) -> Result<usize, AppError> {
+    bytes
+        .get(position..position + BYTES_PER_LENGTH_OFFSET)
+        .map(|offset| u32::from_le_bytes(offset.try_into().unwrap()) as usize)
+        .ok_or_else(|| invalid(format!("Offset at {position} is out of bounds")))
+}
+
+// slices the serialization of a container into the serialization of its fields
+fn split_fields<'a>(fields: &[&SszType], bytes: &'a [u8]) -> Result<Vec<&'a [u8]>, AppError> {
+    let mut position = 0;
+    // fixed size fields are set right away, variable size ones once their end is known
+    let mut parts: Vec<Option<&[u8]>> = Vec::with_capacity(fields.len());
+    let mut offsets: Vec<(usize, usize)> = vec![];
+    for field in fields {
+        match field.fixed_size() {
+            Some(size) => {
+                let part = bytes
+                    .get(position..position + size)
+                    .ok_or_else(|| invalid("Container is too short".to_string()))?;
+                parts.push(Some(part));
+                position += size;
+            }
+            None => {
+                offsets.push((parts.len(), read_offset(bytes, position)?));
+                parts.push(None);
+                position += BYTES_PER_LENGTH_OFFSET;
+            }
+        }
+    }
+    match offsets.first() {
+        None if position != bytes.len() => {
+            return Err(invalid(format!(
+                "Container of {} bytes instead of {position}",
+                bytes.len()
+            )))
+        }
+        Some((_, first)) if *first != position => {
+            return Err(invalid(format!(
+                "First offset {first} does not follow the fixed part of {position} bytes"
+            )))
+        }
+        _ => {}
+    }
+    for (index, (field, start)) in offsets.iter().enumerate() {
+        let end = offsets
+            .get(index + 1)
+            .map(|(_, end)| *end)
+            .unwrap_or(bytes.len());
+        let part = bytes
+            .get(*start..end)
+            .ok_or_else(|| invalid(format!("Invalid offsets {start}..{end}")))?;
+        parts[*field] = Some(part);
+    }
+    Ok(parts.into_iter().flatten().collect())
+}
+
+// slices the serialization of a vector or list into the serialization of its elements
+fn split_elements<'a>(element: &SszType, bytes: &'a [u8]) -> Result<Vec<&'a [u8]>, AppError> {
+    if let Some(size) = element.fixed_size() {
+        if size == 0 || !bytes.len().is_multiple_of(size) {
+            return Err(invalid(format!(
+                "{} bytes are not a multiple of the element size {size}",
+                bytes.len()
+            )));
+        }
+        return Ok(bytes.chunks(size).collect());
+    }
+    if bytes.is_empty() {
+        return Ok(vec![]);
+    }
+    let first = read_offset(bytes, 0)?;
+    if !first.is_multiple_of(BYTES_PER_LENGTH_OFFSET) || first == 0 {
+        return Err(invalid(format!("Invalid first offset {first}")));
+    }
+    let offsets = (0..first / BYTES_PER_LENGTH_OFFSET)
+        .map(|index| read_offset(bytes, index * BYTES_PER_LENGTH_OFFSET))
+        .collect::<Result<Vec<usize>, AppError>>()?;
+    offsets
+        .iter()
+        .enumerate()
+        .map(|(index, start)| {
+            let end = offsets.get(index + 1).copied().unwrap_or(bytes.len());
+            bytes
+                .get(*start..end)
+                .ok_or_else(|| invalid(format!("Invalid offsets {start}..{end}")))
+        })
+        .collect()
+}
+
+fn elements_root(
+    element: &SszType,
+    elements: &[&[u8]],
+    bytes: &[u8],
+    limit: usize,
+) -> Result<Root, AppError> {
+    if element.is_basic() {
+        let size = element.fixed_size().unwrap_or(1);
+        return Ok(merkleize(&pack(bytes), chunk_count(limit * size)));
+    }
+    let roots = elements
+        .iter()
+        .map(|element_bytes| element.hash_tree_root(element_bytes))
+        .collect::<Result<Vec<Root>, AppError>>()?;
+    Ok(merkleize(&roots, limit))
+}
+
+// the bits of a bitlist without its delimiting bit, and their number
+fn bitlist_bits(bytes: &[u8]) -> Result<(Vec<u8>, usize), AppError> {
+    let last = *bytes
+        .last()
+        .filter(|last| **last != 0)
+        .ok_or_else(|| invalid("Bitlist without delimiting bit".to_string()))?;
+    let delimiter = 7 - last.leading_zeros() as usize;
+    let length = (bytes.len() - 1) * 8 + delimiter;
+    let mut bits = bytes.to_vec();
+    let last_index = bits.len() - 1;
+    bits[last_index] ^= 1 << delimiter;
+    if delimiter == 0 {
+        bits.pop();
+    }
+    Ok((bits, length))
+}
+
+fn pack(bytes: &[u8]) -> Vec<Root> {
+    bytes
+        .chunks(BYTES_PER_CHUNK)
+        .map(|chunk| {
+            let mut root = [0u8; 32];
+            root[..chunk.len()].copy_from_slice(chunk);
+            root
+        })
+        .collect()
+}
+
+fn hash(left: &[u8], right: &[u8]) -> Root {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+fn mix_in_length(root: Root, length: usize) -> Root {
+    let mut length_chunk = [0u8; 32];
+    length_chunk[..8].copy_from_slice(&(length as u64).to_le_bytes());
+    hash(&root, &length_chunk)
+}
+
+// roots of trees of zero chunks, by depth
+fn zero_hashes() -> &'static [Root] {
+    static ZERO_HASHES: OnceLock<Vec<Root>> = OnceLock::new();
+    ZERO_HASHES.get_or_init(|| {
+        let mut zero_hashes = vec![[0u8; 32]];
+        for level in 0..64 {
+            zero_hashes.push(hash(&zero_hashes[level], &zero_hashes[level]));
+        }
+        zero_hashes
+    })
+}
+
+// merkle root of the chunks, padded with zero chunks up to the next power of two of the limit
+pub fn merkleize(chunks: &[Root], limit: usize) -> Root {
+    let depth = limit.max(1).next_power_of_two().trailing_zeros() as usize;
+    let zero_hashes = zero_hashes();
+    if chunks.is_empty() {
+        return zero_hashes[depth];
+    }
+    let mut layer = chunks.to_vec();
+    for zero_hash in zero_hashes.iter().take(depth) {
+        if layer.len() % 2 == 1 {
+            layer.push(*zero_hash);
+        }
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash(&pair[0], &pair[1]))
+            .collect();
+    }
+    layer[0]
+}
@@ -0,0 +1,118 @@
+use crate::download::{to_hex, verified_checkpoint};
+use crate::errors::AppError;
+use crate::forks::{beacon_block_header, ForkName};
+use crate::network::Network;
+use crate::processor::DisplayableResult;
+use colored::Colorize;
+
+// position of the state root in the serialization of a BeaconBlockHeader, after its slot,
+// proposer index and parent root
+const HEADER_STATE_ROOT: std::ops::Range<usize> = 48..80;
+
+// The roots of a local state, matching the canonical checkpoint
+#[derive(Debug, Clone)]
+pub struct StateVerification {
+    pub fork: ForkName,
+    pub slot: u64,
+    pub state_root: String,
+    pub block_root: String,
+}
+
+// Whether the state is the post state of its latest block, which it is as long as the state root
+// of the latest block header is not filled in, as happens when the next slot is processed
+pub fn is_block_state(network: &Network, state: &[u8]) -> Result<bool, AppError> {
+    let fork = ForkName::of_state(network, state)?;
+    let latest_block_header = fork.beacon_state().field(state, "latest_block_header")?;
+    Ok(latest_block_header[HEADER_STATE_ROOT]
+        .iter()
+        .all(|b| *b == 0))
+}
+
+// the first slot of the epoch of the canonical checkpoint of the result
+pub fn epoch_start_slot(network: &Network, result: &DisplayableResult) -> Result<u64, AppError> {
+    let (block_info, _) = verified_checkpoint(result)?;
+    block_info
+        .epoch
+        .parse::<u64>()
+        .ok()
+        .and_then(|epoch| epoch.checked_mul(network.slots_per_epoch))
+        .ok_or(AppError::NoVerifiedCheckpoint {
+            message: format!("Invalid finalized epoch {}", block_info.epoch),
+        })
+}
+
+// Checks that an SSZ encoded state is the state of the canonical checkpoint of the result: the
+// root of its latest block header must be the finalized block root, and the state must either be
+// the post state of that block, with the verified state root, or the state advanced through the
+// empty slots after it up to the first slot of the finalized epoch, with the state root the
+// providers agree on for that slot.
+pub fn verify_state(
+    network: &Network,
+    result: &DisplayableResult,
+    state: &[u8],
+    epoch_start_state_root: Option<&str>,
+) -> Result<StateVerification, AppError> {
+    let (block_info, header) = verified_checkpoint(result)?;
+    let fork = ForkName::of_state(network, state)?;
+    let state_type = fork.beacon_state();
+    let state_hash = state_type.hash_tree_root(state)?;
+    let slot = u64::from_le_bytes(state_type.field(state, "slot")?.try_into().map_err(|_| {
+        AppError::InvalidSsz {
+            message: "Invalid slot".to_string(),
+        }
+    })?);
+
+    // the state root of the latest block header is only filled in when the next slot is
+    // processed, until then it is the root of the state itself
+    let is_block_state = is_block_state(network, state)?;
+    let mut latest_block_header = state_type.field(state, "latest_block_header")?.to_vec();
+    if is_block_state {
+        latest_block_header[HEADER_STATE_ROOT].copy_from_slice(&state_hash);
+    }
+    let block_root = to_hex(&beacon_block_header().hash_tree_root(&latest_block_header)?);
+    let state_root = to_hex(&state_hash);
+
+    if !block_root.eq_ignore_ascii_case(&block_info.root) {
+        return Err(AppError::StateMismatch {
+            message: format!(
+                "latest block header hashes to {block_root} instead of {}",
+                block_info.root
+            ),
+        });
+    }
+    let expected_state_root = if is_block_state {
+        header.state_root.as_str()
+    } else {
+        let epoch_start = epoch_start_slot(network, result)?;
+        if slot != epoch_start {
+            return Err(AppError::StateMismatch {
+                message: format!(
+                    "state at slot {slot} is neither the state of the finalized block nor the state at the start of epoch {}",
+                    block_info.epoch
+                ),
+            });
+        }
+        epoch_start_state_root.ok_or(AppError::StateMismatch {
+            message: format!("the state root at slot {epoch_start} is not known"),
+        })?
+    };
+    if !state_root.eq_ignore_ascii_case(expected_state_root) {
+        return Err(AppError::StateMismatch {
+            message: format!("state hashes to {state_root} instead of {expected_state_root}"),
+        });
+    }
+    Ok(StateVerification {
+        fork,
+        slot,
+        state_root,
+        block_root,
+    })
+}
+
+pub fn print_state_verification(verification: &StateVerification) {
+    println!("{}", "State verified:".blue().bold());
+    println!("\t Fork: {}", verification.fork.to_string().green());
+    println!("\t Slot: {}", verification.slot.to_string().green());
+    println!("\t State root: {}", verification.state_root.green());
+    println!("\t Block root: {}", verification.block_root.green());
+}
@@ -0,0 +1,175 @@
+use crate::client::{CheckpointClient, HttpClient};
+use crate::errors::AppError;
+use crate::processor::{DisplayableResult, QuorumStatus};
+use colored::Colorize;
+
+// What changed in the finalized checkpoint since the previous poll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    // the first checkpoint seen, or one at a later epoch than the previous one
+    Finalized {
+        root: String,
+        epoch: u64,
+    },
+    // a checkpoint at an earlier epoch than the previous one
+    Regressed {
+        root: String,
+        epoch: u64,
+        previous_root: String,
+        previous_epoch: u64,
+    },
+    // a different checkpoint at the same epoch as the previous one
+    Replaced {
+        root: String,
+        epoch: u64,
+        previous_root: String,
+    },
+    // no checkpoint reached quorum, with the roots the providers returned
+    NoQuorum {
+        status: QuorumStatus,
+        roots: Vec<String>,
+    },
+    // the finalized epoch lags the wall clock by more than the allowed number of epochs
+    Stalled {
+        epoch: u64,
+        current_epoch: u64,
+    },
+}
+
+// Keeps the last finalized checkpoint between polls, to tell how finality moved
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    last: Option<(String, u64)>,
+    stall_epochs: u64,
+}
+
+impl Watcher {
+    pub fn new(stall_epochs: u64) -> Self {
+        Self {
+            last: None,
+            stall_epochs,
+        }
+    }
+
+    pub fn observe(&mut self, result: &DisplayableResult, current_epoch: u64) -> Vec<WatchEvent> {
+        let mut events = vec![];
+        let checkpoint = result
+            .canonical_checkpoint()
+            .filter(|_| result.status() == QuorumStatus::Canonical)
+            .and_then(|block_info| {
+                let epoch = block_info.epoch.parse::<u64>().ok()?;
+                Some((block_info.root.clone(), epoch))
+            });
+        let Some((root, epoch)) = checkpoint else {
+            events.push(WatchEvent::NoQuorum {
+                status: result.status(),
+                roots: result
+                    .tally
+                    .iter()
+                    .map(|tally| tally.root.clone())
+                    .collect(),
+            });
+            return events;
+        };
+
+        match self.last.take() {
+            Some((previous_root, previous_epoch)) if epoch < previous_epoch => {
+                events.push(WatchEvent::Regressed {
+                    root: root.clone(),
+                    epoch,
+                    previous_root,
+                    previous_epoch,
+                })
+            }
+            Some((previous_root, previous_epoch))
+                if epoch == previous_epoch && !root.eq_ignore_ascii_case(&previous_root) =>
+            {
+                events.push(WatchEvent::Replaced {
+                    root: root.clone(),
+                    epoch,
+                    previous_root,
+                })
+            }
+            Some((_, previous_epoch)) if epoch == previous_epoch => {}
+            _ => events.push(WatchEvent::Finalized {
+                root: root.clone(),
+                epoch,
+            }),
+        }
+        if current_epoch.saturating_sub(epoch) > self.stall_epochs {
+            events.push(WatchEvent::Stalled {
+                epoch,
+                current_epoch,
+            });
+        }
+        self.last = Some((root, epoch));
+        events
+    }
+}
+
+pub fn print_watch_event(event: &WatchEvent, current_epoch: u64) {
+    let prefix = format!("[epoch {current_epoch}]").blue();
+    match event {
+        WatchEvent::Finalized { root, epoch } => {
+            println!(
+                "{prefix} {}: {} (epoch {epoch})",
+                "Finalized".green().bold(),
+                root.green()
+            )
+        }
+        WatchEvent::Regressed {
+            root,
+            epoch,
+            previous_root,
+            previous_epoch,
+        } => println!(
+            "{prefix} {}: {} (epoch {epoch}) after {previous_root} (epoch {previous_epoch})",
+            "Regressed".red().bold(),
+            root.red()
+        ),
+        WatchEvent::Replaced {
+            root,
+            epoch,
+            previous_root,
+        } => println!(
+            "{prefix} {}: {} replaced {previous_root} at epoch {epoch}",
+            "Replaced".red().bold(),
+            root.red()
+        ),
+        WatchEvent::NoQuorum { status, roots } => println!(
+            "{prefix} {}: {status} ({})",
+            "No quorum".yellow().bold(),
+            roots.join(", ").yellow()
+        ),
+        WatchEvent::Stalled {
+            epoch,
+            current_epoch,
+        } => println!(
+            "{prefix} {}: finalized epoch {epoch} is {} epochs behind",
+            "Stalled".yellow().bold(),
+            current_epoch - epoch
+        ),
+    }
+}
+
+// Polls the providers once per epoch, one slot after the epoch starts, and prints how finality
+// moved. Only returns on errors that polling
+// again would not fix, like an unknown network.
+pub async fn watch<C: HttpClient>(
+    checkpoint_client: &CheckpointClient<C>,
+    network: &str,
+    stall_epochs: u64,
+) -> Result<(), AppError> {
+    let network_config = checkpoint_client.network(network)?;
+    let mut watcher = Watcher::new(stall_epochs);
+    loop {
+        let result = checkpoint_client
+            .fetch_finality_checkpoints(network)
+            .await?;
+        let current_epoch = network_config.current_epoch();
+        for event in watcher.observe(&result, current_epoch) {
+            print_watch_event(&event, current_epoch);
+        }
+        tokio::time::sleep(network_config.until_next_epoch()).await;
+    }
+}
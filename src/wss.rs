@@ -0,0 +1,125 @@
+use crate::errors::AppError;
+use crate::processor::{DisplayableResult, QuorumStatus};
+use std::fmt;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+// Consensus clients the weak subjectivity checkpoint flags can be printed for
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BeaconClient {
+    Lighthouse,
+    Prysm,
+    Teku,
+    Nimbus,
+    Lodestar,
+}
+
+impl fmt::Display for BeaconClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BeaconClient::Lighthouse => write!(f, "lighthouse"),
+            BeaconClient::Prysm => write!(f, "prysm"),
+            BeaconClient::Teku => write!(f, "teku"),
+            BeaconClient::Nimbus => write!(f, "nimbus"),
+            BeaconClient::Lodestar => write!(f, "lodestar"),
+        }
+    }
+}
+
+impl FromStr for BeaconClient {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "lighthouse" => Ok(BeaconClient::Lighthouse),
+            "prysm" => Ok(BeaconClient::Prysm),
+            "teku" => Ok(BeaconClient::Teku),
+            "nimbus" => Ok(BeaconClient::Nimbus),
+            "lodestar" => Ok(BeaconClient::Lodestar),
+            other => Err(AppError::InvalidClient {
+                message: format!(
+                    "Unknown client {other}. Expected one of lighthouse, prysm, teku, nimbus or lodestar"
+                ),
+            }),
+        }
+    }
+}
+
+impl BeaconClient {
+    // flags to checkpoint sync from the url and to check the chain against the weak subjectivity
+    // checkpoint
+    pub fn flags(&self, ws_checkpoint: &WsCheckpoint) -> Vec<String> {
+        let (url, checkpoint) = (&ws_checkpoint.endpoint, ws_checkpoint.to_string());
+        match self {
+            BeaconClient::Lighthouse => vec![
+                format!("--checkpoint-sync-url={url}"),
+                format!("--wss-checkpoint={checkpoint}"),
+            ],
+            BeaconClient::Prysm => vec![
+                format!("--checkpoint-sync-url={url}"),
+                format!("--genesis-beacon-api-url={url}"),
+                format!("--weak-subjectivity-checkpoint={checkpoint}"),
+            ],
+            BeaconClient::Teku => vec![
+                format!("--checkpoint-sync-url={url}"),
+                format!("--ws-checkpoint={checkpoint}"),
+            ],
+            BeaconClient::Nimbus => vec![
+                format!("--external-beacon-api-url={url}"),
+                format!("--trusted-block-root={}", ws_checkpoint.root),
+                format!("--weak-subjectivity-checkpoint={checkpoint}"),
+            ],
+            BeaconClient::Lodestar => vec![
+                format!("--checkpointSyncUrl={url}"),
+                format!("--wssCheckpoint={checkpoint}"),
+            ],
+        }
+    }
+}
+
+// The finalized checkpoint that reached quorum, along with one of the providers that agreed on it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WsCheckpoint {
+    pub root: String,
+    pub epoch: String,
+    pub endpoint: String,
+}
+
+// in the 0xroot:epoch form expected by the clients
+impl fmt::Display for WsCheckpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.root, self.epoch)
+    }
+}
+
+impl WsCheckpoint {
+    pub fn from_result(result: &DisplayableResult) -> Result<Self, AppError> {
+        let status = result.status();
+        let checkpoint = result.canonical_checkpoint();
+        let endpoint = result
+            .canonical
+            .iter()
+            .flat_map(|canonical| canonical.values().flatten())
+            .map(|value| value.endpoint.clone())
+            .next();
+        match (status, checkpoint, endpoint) {
+            (QuorumStatus::Canonical, Some(block_info), Some(endpoint)) => Ok(WsCheckpoint {
+                root: block_info.root.clone(),
+                epoch: block_info.epoch.clone(),
+                endpoint,
+            }),
+            _ => Err(AppError::NoVerifiedCheckpoint {
+                message: format!("No weak subjectivity checkpoint, quorum status is {status:?}"),
+            }),
+        }
+    }
+}
+
+// Prints the weak subjectivity checkpoint, or the flags of the client when one is given, without
+// any decoration so it can be used from scripts
+pub fn print_ws_checkpoint(ws_checkpoint: &WsCheckpoint, client: Option<BeaconClient>) {
+    match client {
+        Some(client) => println!("{}", client.flags(ws_checkpoint).join(" ")),
+        None => println!("{ws_checkpoint}"),
+    }
+}
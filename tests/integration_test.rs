@@ -1,16 +1,73 @@
+#![allow(clippy::get_first)]
+
 extern crate core;
 
 use async_trait::async_trait;
 use checkpointq_lib::client::{
-    BlockInfo, CheckpointClient, Data, EndpointsConfig, HttpClient, StateId, SuccessEndpointPayload,
+    BeaconBlockHeader, BlockInfo, CheckpointClient, Data, Endpoint, EndpointsConfig, FetchOptions,
+    Fork, ForkPayload, Genesis, GenesisPayload, HeaderData, HeaderPayload, HttpClient,
+    RequestConfig, SignedBeaconBlockHeader, StateId, SuccessEndpointPayload, SyncCheck, SyncIssue,
+    SyncStatus, SyncingPayload,
 };
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tower::ServiceExt;
 
+use checkpointq_lib::checkpoint_server::{BindAddress, CheckPointMiddleware, Listener};
+use checkpointq_lib::download::{download_checkpoint, to_hex};
 use checkpointq_lib::errors::AppError;
+use checkpointq_lib::exit_status::ExitStatus;
+use checkpointq_lib::forks::ForkName;
+use checkpointq_lib::network::Network;
+use checkpointq_lib::processor::{
+    table, verify_headers, CheckpointKind, CheckpointTally, DisplayableResult, OutputFormat,
+    QuorumStatus, Report,
+};
+use checkpointq_lib::quorum::QuorumPolicy;
+use checkpointq_lib::ssz::{merkleize, SszType};
+use checkpointq_lib::verify::{epoch_start_slot, is_block_state, verify_state};
+use checkpointq_lib::watch::{WatchEvent, Watcher};
+use checkpointq_lib::wss::{BeaconClient, WsCheckpoint};
 
-use checkpointq_lib::args::Network::Sepolia;
 use reqwest::Response;
 
+const SEPOLIA_GENESIS_VALIDATORS_ROOT: &str =
+    "0xd8ea171f3c94aea21ebc42a1ed61052acf3f9209c00e4efbaaddac09ed9b8078";
+const SEPOLIA_FORK_VERSION: &str = "0x90000075";
+
+// answers the genesis and fork requests made to check that a provider is on the given chain
+fn chain_response(
+    path: &str,
+    genesis_validators_root: &str,
+    fork_version: &str,
+) -> Option<Response> {
+    let body = if path.ends_with("/eth/v1/beacon/genesis") {
+        serde_json::to_string(&GenesisPayload {
+            data: Genesis {
+                genesis_time: "1655733600".to_string(),
+                genesis_validators_root: genesis_validators_root.to_string(),
+                genesis_fork_version: "0x90000069".to_string(),
+            },
+        })
+    } else if path.ends_with("/eth/v1/beacon/states/head/fork") {
+        serde_json::to_string(&ForkPayload {
+            data: Fork {
+                previous_version: "0x90000074".to_string(),
+                current_version: fork_version.to_string(),
+                epoch: "272640".to_string(),
+            },
+        })
+    } else {
+        return None;
+    };
+    Some(Response::from(http::response::Response::new(body.unwrap())))
+}
+
+fn sepolia_response(path: &str) -> Option<Response> {
+    chain_response(path, SEPOLIA_GENESIS_VALIDATORS_ROOT, SEPOLIA_FORK_VERSION)
+}
+
 type Req = String;
 type BlockRootRes = String;
 type ErrorRes = String;
@@ -18,6 +75,7 @@ type ErrorRes = String;
 struct MockClient {
     base: SuccessEndpointPayload,
     paths: Vec<(Req, Result<BlockRootRes, ErrorRes>)>,
+    current_justified: Vec<(Req, BlockRootRes)>,
 }
 impl MockClient {
     pub fn new(paths: Vec<(Req, Result<BlockRootRes, ErrorRes>)>) -> Self {
@@ -39,13 +97,22 @@ impl MockClient {
                 },
             },
             paths,
+            current_justified: vec![],
         }
     }
+
+    pub fn with_current_justified(mut self, current_justified: Vec<(Req, BlockRootRes)>) -> Self {
+        self.current_justified = current_justified;
+        self
+    }
 }
 
 #[async_trait]
 impl HttpClient for MockClient {
     async fn send_request(&self, path: String) -> Result<Response, AppError> {
+        if let Some(response) = sepolia_response(&path) {
+            return Ok(response);
+        }
         let responses: (
             Vec<(Req, Result<BlockRootRes, ErrorRes>)>,
             Vec<(Req, Result<BlockRootRes, ErrorRes>)>,
@@ -73,17 +140,31 @@ impl HttpClient for MockClient {
             ..Clone::clone(&self.base)
         };
         if !success_responses.is_empty() {
-            payload.data.finalized.root =
-                success_responses.into_iter().next().unwrap().ok().unwrap();
+            // a mocked response of the form root:epoch also sets the finalized epoch
+            let response = success_responses.into_iter().next().unwrap().ok().unwrap();
+            let (root, epoch) = response.split_once(':').unwrap_or((&response, ""));
+            payload.data.finalized.root = root.to_string();
+            payload.data.finalized.epoch = epoch.to_string();
+            if let Some((_, root)) = self
+                .current_justified
+                .iter()
+                .find(|(req, _)| path.contains(req))
+            {
+                payload.data.current_justified.root = root.clone();
+            }
             Ok(Response::from(http::response::Response::new(
                 serde_json::to_string(&payload).unwrap(),
             )))
         } else if !err_responses.is_empty() {
-            Err(AppError::EndpointResponseError(
-                err_responses.into_iter().next().unwrap().err().unwrap(),
-            ))
+            Err(AppError::Connect {
+                endpoint: path,
+                message: err_responses.into_iter().next().unwrap().err().unwrap(),
+            })
         } else {
-            Err(AppError::EndpointResponseError("mock error".to_string()))
+            Err(AppError::Connect {
+                endpoint: path,
+                message: "mock error".to_string(),
+            })
         }
     }
 }
@@ -116,12 +197,16 @@ pub async fn test_only_canonical_results() {
     ];
 
     let endpoint_config = EndpointsConfig {
-        endpoints: HashMap::from([(Sepolia.to_string().to_lowercase(), endpoints)]),
+        endpoints: HashMap::from([(
+            "sepolia".to_string(),
+            endpoints.into_iter().map(Endpoint::from).collect(),
+        )]),
+        ..Default::default()
     };
 
     let checkpoint_client = CheckpointClient::new(client, StateId::Finalized, endpoint_config);
     let result = checkpoint_client
-        .fetch_finality_checkpoints(Sepolia)
+        .fetch_finality_checkpoints("sepolia")
         .await
         .unwrap();
     // assert only canonical results are returned
@@ -177,12 +262,16 @@ pub async fn test_only_non_canonical_results() {
     ];
 
     let endpoint_config = EndpointsConfig {
-        endpoints: HashMap::from([(Sepolia.to_string().to_lowercase(), endpoints)]),
+        endpoints: HashMap::from([(
+            "sepolia".to_string(),
+            endpoints.into_iter().map(Endpoint::from).collect(),
+        )]),
+        ..Default::default()
     };
 
     let checkpoint_client = CheckpointClient::new(client, StateId::Finalized, endpoint_config);
     let result = checkpoint_client
-        .fetch_finality_checkpoints(Sepolia)
+        .fetch_finality_checkpoints("sepolia")
         .await
         .unwrap();
     // assert only non canonical results are returned
@@ -236,12 +325,16 @@ pub async fn test_only_failure_results() {
     ];
 
     let endpoint_config = EndpointsConfig {
-        endpoints: HashMap::from([(Sepolia.to_string().to_lowercase(), endpoints)]),
+        endpoints: HashMap::from([(
+            "sepolia".to_string(),
+            endpoints.into_iter().map(Endpoint::from).collect(),
+        )]),
+        ..Default::default()
     };
 
     let checkpoint_client = CheckpointClient::new(client, StateId::Finalized, endpoint_config);
     let result = checkpoint_client
-        .fetch_finality_checkpoints(Sepolia)
+        .fetch_finality_checkpoints("sepolia")
         .await
         .unwrap();
     // assert only error results are returned
@@ -252,15 +345,15 @@ pub async fn test_only_failure_results() {
     let failure_result = result.failure;
     assert_eq!(
         &failure_result.get(0).unwrap().payload.to_string(),
-        &format!("Error: {}", &error0.to_string())
+        &format!("Could not connect to http://www.good1.com: {}", &error0)
     );
     assert_eq!(
         &failure_result.get(1).unwrap().payload.to_string(),
-        &format!("Error: {}", &error1.to_string())
+        &format!("Could not connect to http://www.good2.com: {}", &error1)
     );
     assert_eq!(
         &failure_result.get(2).unwrap().payload.to_string(),
-        &format!("Error: {}", &error2.to_string())
+        &format!("Could not connect to http://www.good3.com: {}", &error2)
     );
 }
 
@@ -310,12 +403,16 @@ pub async fn test_results_but_no_canonical() {
     ];
 
     let endpoint_config = EndpointsConfig {
-        endpoints: HashMap::from([(Sepolia.to_string().to_lowercase(), endpoints)]),
+        endpoints: HashMap::from([(
+            "sepolia".to_string(),
+            endpoints.into_iter().map(Endpoint::from).collect(),
+        )]),
+        ..Default::default()
     };
 
     let checkpoint_client = CheckpointClient::new(client, StateId::Finalized, endpoint_config);
     let result = checkpoint_client
-        .fetch_finality_checkpoints(Sepolia)
+        .fetch_finality_checkpoints("sepolia")
         .await
         .expect("result missing");
     // assert only non canonical results are returned
@@ -325,3 +422,2563 @@ pub async fn test_results_but_no_canonical() {
     assert_eq!(non_canonical_map.keys().len(), 4);
     assert_eq!(non_canonical_map.values().flatten().count(), 5);
 }
+
+fn mocks_with_roots(roots: Vec<Result<&str, &str>>) -> (MockClient, Vec<String>) {
+    let mocks: Vec<(Req, Result<BlockRootRes, ErrorRes>)> = roots
+        .into_iter()
+        .enumerate()
+        .map(|(index, root)| {
+            (
+                format!("http://www.endpoint{index}.com"),
+                root.map(|r| r.to_string()).map_err(|e| e.to_string()),
+            )
+        })
+        .collect();
+    let endpoints = mocks.iter().map(|(req, _)| req.clone()).collect();
+    (MockClient::new(mocks), endpoints)
+}
+
+fn tally_for<'a>(result: &'a DisplayableResult, root: &str) -> &'a CheckpointTally {
+    result
+        .tally
+        .iter()
+        .find(|tally| tally.root == root)
+        .unwrap()
+}
+
+fn sepolia_config(endpoints: Vec<String>) -> EndpointsConfig {
+    EndpointsConfig {
+        endpoints: HashMap::from([(
+            "sepolia".to_string(),
+            endpoints.into_iter().map(Endpoint::from).collect(),
+        )]),
+        ..Default::default()
+    }
+}
+
+#[test]
+pub fn test_quorum_policy_parsing() {
+    assert_eq!(
+        "endpoints:2/3".parse::<QuorumPolicy>().unwrap(),
+        QuorumPolicy::EndpointFraction {
+            numerator: 2,
+            denominator: 3
+        }
+    );
+    assert_eq!(
+        "responders:1/2".parse::<QuorumPolicy>().unwrap(),
+        QuorumPolicy::ResponderFraction {
+            numerator: 1,
+            denominator: 2
+        }
+    );
+    assert_eq!(
+        "min:4".parse::<QuorumPolicy>().unwrap(),
+        QuorumPolicy::MinCount(4)
+    );
+    assert_eq!(
+        "unanimous".parse::<QuorumPolicy>().unwrap(),
+        QuorumPolicy::Unanimous
+    );
+    assert!("endpoints:3/2".parse::<QuorumPolicy>().is_err());
+    assert!("endpoints:1/0".parse::<QuorumPolicy>().is_err());
+    assert!("endpoints:1/1".parse::<QuorumPolicy>().is_err());
+    assert!("responders:2/2".parse::<QuorumPolicy>().is_err());
+    assert!("min:0".parse::<QuorumPolicy>().is_err());
+    assert!("majority".parse::<QuorumPolicy>().is_err());
+    assert_eq!(QuorumPolicy::default().to_string(), "endpoints:2/3");
+    // weights large enough to overflow the product of the total and the numerator
+    assert_eq!(
+        QuorumPolicy::default().threshold(u64::MAX, 0),
+        12297829382473034411
+    );
+}
+
+#[tokio::test]
+pub async fn test_default_policy_counts_providers_not_roots() {
+    // two of three agreeing is not more than 2/3 of the configured endpoints
+    let (client, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash2")]);
+    let checkpoint_client =
+        CheckpointClient::new(client, StateId::Finalized, sepolia_config(endpoints));
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert!(result.canonical.is_none());
+    assert_eq!(result.threshold, 3);
+    assert_eq!(result.quorum_policy, QuorumPolicy::default());
+    assert_eq!(result.non_canonical.unwrap().keys().len(), 2);
+}
+
+#[tokio::test]
+pub async fn test_responder_fraction_ignores_failures() {
+    let (client, endpoints) = mocks_with_roots(vec![
+        Ok("Hash1"),
+        Ok("Hash1"),
+        Ok("Hash1"),
+        Err("down"),
+        Err("down"),
+    ]);
+    let checkpoint_client =
+        CheckpointClient::new(client, StateId::Finalized, sepolia_config(endpoints));
+
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert!(result.canonical.is_none());
+    assert_eq!(result.threshold, 4);
+
+    let result = checkpoint_client
+        .fetch_finality_checkpoints_with_options(
+            "sepolia",
+            &FetchOptions {
+                quorum_policy: "responders:2/3".parse::<QuorumPolicy>().unwrap(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.threshold, 3);
+    assert!(result.canonical.unwrap().contains_key("Hash1"));
+    assert_eq!(result.failure.len(), 2);
+}
+
+#[tokio::test]
+pub async fn test_min_count_and_unanimous_policies() {
+    let (client, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Err("down")]);
+    let mut config = sepolia_config(endpoints);
+    config.quorum = Some(QuorumPolicy::MinCount(2));
+    let checkpoint_client = CheckpointClient::new(client, StateId::Finalized, config);
+
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert_eq!(result.quorum_policy, QuorumPolicy::MinCount(2));
+    assert!(result.canonical.unwrap().contains_key("Hash1"));
+
+    let checkpoint_client = checkpoint_client.with_quorum_policy(QuorumPolicy::Unanimous);
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert_eq!(result.threshold, 3);
+    assert!(result.canonical.is_none());
+}
+
+#[test]
+pub fn test_endpoints_config_with_weights() {
+    let config: EndpointsConfig = serde_yaml::from_str(
+        r#"
+endpoints:
+  sepolia:
+    - http://www.endpoint0.com
+    - url: http://www.endpoint1.com
+      weight: 3
+    - url: http://www.endpoint2.com
+      operator: acme
+"#,
+    )
+    .unwrap();
+    let endpoints = config.endpoints.get("sepolia").unwrap();
+    assert_eq!(
+        endpoints.iter().map(|e| e.weight).collect::<Vec<u64>>(),
+        vec![1, 3, 1]
+    );
+    assert_eq!(endpoints[1].url, "http://www.endpoint1.com");
+    assert_eq!(endpoints[1].operator(), "http://www.endpoint1.com");
+    assert_eq!(endpoints[2].operator(), "acme");
+}
+
+#[test]
+pub fn test_endpoints_config_with_networks() {
+    let config: EndpointsConfig = serde_yaml::from_str(
+        r#"
+endpoints:
+  devnet:
+    - http://www.endpoint0.com
+networks:
+  devnet:
+    genesis_validators_root: "0x83431ec7fcf92cfc44947fc0418e831c25e1d0806590231c439830db7ad54fda"
+    genesis_time: 1700000000
+    seconds_per_slot: 6
+  mainnet:
+    genesis_validators_root: "0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95"
+    genesis_time: 1606824023
+    slots_per_epoch: 8
+"#,
+    )
+    .unwrap();
+
+    let devnet = config.network("Devnet").unwrap();
+    assert_eq!(devnet.genesis_time, 1700000000);
+    assert_eq!(devnet.seconds_per_slot, 6);
+    assert_eq!(devnet.slots_per_epoch, 32);
+
+    // config entries override the built-in networks, which remain available otherwise
+    assert_eq!(config.network("mainnet").unwrap().slots_per_epoch, 8);
+    assert_eq!(config.network("gnosis").unwrap().seconds_per_slot, 5);
+    assert!(config.network("holesky").is_ok());
+    assert!(config.network("unknown").is_err());
+}
+
+#[tokio::test]
+async fn test_custom_network() {
+    let (client, endpoints) = mocks_with_roots(vec![Ok("a"), Ok("a"), Ok("a")]);
+    let mut config = EndpointsConfig {
+        endpoints: HashMap::from([(
+            "devnet".to_string(),
+            endpoints.into_iter().map(Endpoint::from).collect(),
+        )]),
+        ..Default::default()
+    };
+    let checkpoint_client = CheckpointClient::new(client, StateId::Finalized, config.clone());
+    let error = checkpoint_client
+        .fetch_finality_checkpoints("devnet")
+        .await
+        .unwrap_err();
+    assert!(matches!(error, AppError::UnknownNetwork { .. }));
+
+    config.networks.insert(
+        "devnet".to_string(),
+        serde_yaml::from_str(&format!(
+            "genesis_validators_root: \"{SEPOLIA_GENESIS_VALIDATORS_ROOT}\"\ngenesis_time: 1700000000"
+        ))
+        .unwrap(),
+    );
+    let (client, _) = mocks_with_roots(vec![Ok("a"), Ok("a"), Ok("a")]);
+    let checkpoint_client = CheckpointClient::new(client, StateId::Finalized, config);
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("DevNet")
+        .await
+        .unwrap();
+    assert!(result.canonical.unwrap().contains_key("a"));
+}
+
+#[tokio::test]
+pub async fn test_weighted_quorum() {
+    // a heavily weighted endpoint outvotes two community providers
+    let (client, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash2"), Ok("Hash2")]);
+    let mut config = sepolia_config(endpoints);
+    config.endpoints.get_mut("sepolia").unwrap()[0].weight = 5;
+    config.quorum = Some("responders:1/2".parse::<QuorumPolicy>().unwrap());
+    let checkpoint_client = CheckpointClient::new(client, StateId::Finalized, config);
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert_eq!(result.threshold, 4);
+    assert_eq!(tally_for(&result, "Hash1").weight, 5);
+    assert_eq!(tally_for(&result, "Hash2").weight, 2);
+    let canonical = result.canonical.unwrap();
+    assert_eq!(canonical.get("Hash1").unwrap()[0].weight, 5);
+}
+
+#[tokio::test]
+pub async fn test_one_vote_per_operator() {
+    // three endpoints run by the same operator cannot outvote two independent providers
+    let (client, endpoints) = mocks_with_roots(vec![
+        Ok("Hash1"),
+        Ok("Hash1"),
+        Ok("Hash1"),
+        Ok("Hash2"),
+        Ok("Hash2"),
+    ]);
+    let mut config = sepolia_config(endpoints);
+    for endpoint in config.endpoints.get_mut("sepolia").unwrap()[0..3].iter_mut() {
+        endpoint.operator = Some("acme".to_string());
+    }
+    config.quorum = Some(QuorumPolicy::MinCount(2));
+    let checkpoint_client = CheckpointClient::new(client, StateId::Finalized, config);
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert_eq!(tally_for(&result, "Hash1").weight, 1);
+    assert_eq!(tally_for(&result, "Hash2").weight, 2);
+    assert_eq!(tally_for(&result, "Hash1").distinct_operators, 1);
+    assert!(result.canonical.unwrap().contains_key("Hash2"));
+}
+
+#[tokio::test]
+pub async fn test_min_distinct_operators() {
+    let (client, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]);
+    let mut config = sepolia_config(endpoints);
+    for endpoint in config.endpoints.get_mut("sepolia").unwrap()[0..2].iter_mut() {
+        endpoint.operator = Some("acme".to_string());
+    }
+    config.quorum = Some(QuorumPolicy::MinCount(1));
+    config.min_distinct_operators = Some(3);
+    let checkpoint_client = CheckpointClient::new(client, StateId::Finalized, config);
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert_eq!(result.min_distinct_operators, 3);
+    assert_eq!(tally_for(&result, "Hash1").distinct_operators, 2);
+    assert!(result.canonical.is_none());
+    assert!(result.non_canonical.unwrap().contains_key("Hash1"));
+}
+
+#[tokio::test]
+pub async fn test_same_root_different_epoch_is_not_agreement() {
+    let (client, endpoints) = mocks_with_roots(vec![
+        Ok("Hash1:10"),
+        Ok("Hash1:10"),
+        Ok("Hash1:10"),
+        Ok("Hash1:9"),
+    ]);
+    let mut config = sepolia_config(endpoints);
+    config.quorum = Some(QuorumPolicy::MinCount(3));
+    let checkpoint_client = CheckpointClient::new(client, StateId::Finalized, config);
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    // the three providers agreeing on the full checkpoint still reach quorum
+    let canonical = result.canonical.unwrap();
+    assert_eq!(canonical.get("Hash1").unwrap().len(), 3);
+    assert!(canonical
+        .get("Hash1")
+        .unwrap()
+        .iter()
+        .all(|value| value.payload.data.finalized.epoch == "10"));
+    // and the mismatching epoch is surfaced on its own
+    let epoch_mismatch = result.epoch_mismatch.unwrap();
+    assert_eq!(epoch_mismatch.get("Hash1").unwrap().len(), 4);
+    assert_eq!(result.tally.len(), 2);
+
+    let (client, endpoints) = mocks_with_roots(vec![Ok("Hash1:10"), Ok("Hash1:10"), Ok("Hash1:9")]);
+    let checkpoint_client =
+        CheckpointClient::new(client, StateId::Finalized, sepolia_config(endpoints));
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert!(result.canonical.is_none());
+    assert!(result.non_canonical.is_none());
+    assert!(result.epoch_mismatch.unwrap().contains_key("Hash1"));
+}
+
+#[tokio::test]
+pub async fn test_quorum_over_all_checkpoints() {
+    let (client, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]);
+    let client = client.with_current_justified(
+        endpoints
+            .iter()
+            .enumerate()
+            .map(|(index, endpoint)| (endpoint.clone(), format!("Justified{index}")))
+            .collect(),
+    );
+    let checkpoint_client =
+        CheckpointClient::new(client, StateId::Finalized, sepolia_config(endpoints));
+    let results = checkpoint_client
+        .fetch_all_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert_eq!(
+        results
+            .iter()
+            .map(|result| result.checkpoint)
+            .collect::<Vec<CheckpointKind>>(),
+        CheckpointKind::ALL.to_vec()
+    );
+    assert_eq!(results[0].status(), QuorumStatus::Canonical);
+    assert_eq!(results[0].canonical_checkpoint().unwrap().root, "Hash1");
+    assert_eq!(results[1].status(), QuorumStatus::Conflicting);
+    assert_eq!(results[1].non_canonical.as_ref().unwrap().len(), 3);
+    assert_eq!(results[2].status(), QuorumStatus::Canonical);
+}
+
+#[test]
+pub fn test_state_id_parsing() {
+    let root = format!("0x{}", "ab".repeat(32));
+    assert_eq!("finalized".parse::<StateId>().unwrap(), StateId::Finalized);
+    assert_eq!("head".parse::<StateId>().unwrap(), StateId::Head);
+    assert_eq!("justified".parse::<StateId>().unwrap(), StateId::Justified);
+    assert_eq!("genesis".parse::<StateId>().unwrap(), StateId::Genesis);
+    assert_eq!("1234".parse::<StateId>().unwrap(), StateId::Slot(1234));
+    assert_eq!(
+        root.parse::<StateId>().unwrap(),
+        StateId::Root(root.clone())
+    );
+    assert!("0x1234".parse::<StateId>().is_err());
+    assert!("latest".parse::<StateId>().is_err());
+    assert_eq!(StateId::Slot(1234).to_string(), "1234");
+    assert_eq!(StateId::Root(root.clone()).to_string(), root);
+}
+
+#[tokio::test]
+pub async fn test_fetch_from_slot_state() {
+    // the mock only answers when the requested path contains the slot
+    let mocks: Vec<(Req, Result<BlockRootRes, ErrorRes>)> = (0..3)
+        .map(|index| {
+            (
+                format!(
+                    "http://www.endpoint{index}.com/eth/v1/beacon/states/1234/finality_checkpoints"
+                ),
+                Ok("Hash1".to_string()),
+            )
+        })
+        .collect();
+    let endpoints: Vec<String> = (0..3)
+        .map(|index| format!("http://www.endpoint{index}.com"))
+        .collect();
+    let checkpoint_client = CheckpointClient::new(
+        MockClient::new(mocks),
+        StateId::Finalized,
+        sepolia_config(endpoints),
+    );
+
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert_eq!(result.failure.len(), 3);
+
+    let result = checkpoint_client
+        .fetch_finality_checkpoints_with_options(
+            "sepolia",
+            &FetchOptions {
+                state_id: StateId::Slot(1234),
+                ..checkpoint_client.default_options()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.canonical_checkpoint().unwrap().root, "Hash1");
+}
+
+fn payload_response(root: &str) -> Response {
+    checkpoint_response(root, "")
+}
+
+fn checkpoint_response(root: &str, epoch: &str) -> Response {
+    let mut payload = MockClient::new(vec![]).base;
+    payload.data.finalized.root = root.to_string();
+    payload.data.finalized.epoch = epoch.to_string();
+    Response::from(http::response::Response::new(
+        serde_json::to_string(&payload).unwrap(),
+    ))
+}
+
+fn status_response(status: u16) -> Response {
+    let mut response = http::response::Response::new(String::new());
+    *response.status_mut() = http::StatusCode::from_u16(status).unwrap();
+    Response::from(response)
+}
+
+// answers with the given status for the first `failures` calls to every endpoint
+struct FlakyClient {
+    failures: usize,
+    status: u16,
+    calls: Mutex<HashMap<String, usize>>,
+}
+
+#[async_trait]
+impl HttpClient for FlakyClient {
+    async fn send_request(&self, path: String) -> Result<Response, AppError> {
+        let call = {
+            let mut calls = self.calls.lock().unwrap();
+            let call = calls.entry(path.clone()).or_default();
+            *call += 1;
+            *call
+        };
+        if let Some(response) = sepolia_response(&path) {
+            return Ok(response);
+        }
+        if call <= self.failures {
+            Ok(status_response(self.status))
+        } else {
+            Ok(payload_response("Hash1"))
+        }
+    }
+}
+
+// never answers for endpoints containing "slow", and answers with the root found after
+// "root=" in the path of the other endpoints, Hash1 by default
+struct SlowClient;
+
+#[async_trait]
+impl HttpClient for SlowClient {
+    async fn send_request(&self, path: String) -> Result<Response, AppError> {
+        if path.contains("slow") {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        }
+        if let Some(response) = sepolia_response(&path) {
+            return Ok(response);
+        }
+        let root = path
+            .split_once("root=")
+            .and_then(|(_, rest)| rest.split('/').next())
+            .unwrap_or("Hash1");
+        Ok(payload_response(root))
+    }
+}
+
+fn fast_retries(max_retries: u32) -> RequestConfig {
+    RequestConfig {
+        request_timeout: Duration::from_millis(100),
+        deadline: Duration::from_millis(500),
+        max_retries,
+        initial_backoff: Duration::from_millis(1),
+    }
+}
+
+#[test]
+pub fn test_retry_backoff() {
+    let config = RequestConfig {
+        initial_backoff: Duration::from_millis(250),
+        deadline: Duration::from_secs(30),
+        ..RequestConfig::default()
+    };
+    assert_eq!(config.backoff(0), Duration::from_millis(250));
+    assert_eq!(config.backoff(2), Duration::from_secs(1));
+    // capped by the deadline rather than overflowing
+    assert_eq!(config.backoff(10), Duration::from_secs(30));
+    assert_eq!(config.backoff(40), Duration::from_secs(30));
+    let config = RequestConfig {
+        initial_backoff: Duration::MAX,
+        ..config
+    };
+    assert_eq!(config.backoff(1), Duration::from_secs(30));
+}
+
+#[tokio::test]
+pub async fn test_transient_errors_are_retried() {
+    let endpoints: Vec<String> = (0..3)
+        .map(|index| format!("http://www.endpoint{index}.com"))
+        .collect();
+    let client = FlakyClient {
+        failures: 2,
+        status: 503,
+        calls: Mutex::new(HashMap::new()),
+    };
+    let checkpoint_client = CheckpointClient::new(
+        client,
+        StateId::Finalized,
+        sepolia_config(endpoints.clone()),
+    )
+    .with_request_config(fast_retries(2));
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert!(result.canonical.unwrap().contains_key("Hash1"));
+
+    let client = FlakyClient {
+        failures: 2,
+        status: 503,
+        calls: Mutex::new(HashMap::new()),
+    };
+    let checkpoint_client = CheckpointClient::new(
+        client,
+        StateId::Finalized,
+        sepolia_config(endpoints.clone()),
+    )
+    .with_request_config(fast_retries(1));
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert_eq!(result.failure.len(), 3);
+
+    // client errors are not retried
+    let client = FlakyClient {
+        failures: 1,
+        status: 404,
+        calls: Mutex::new(HashMap::new()),
+    };
+    let checkpoint_client =
+        CheckpointClient::new(client, StateId::Finalized, sepolia_config(endpoints))
+            .with_request_config(fast_retries(2));
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert_eq!(result.failure.len(), 3);
+}
+
+#[tokio::test]
+pub async fn test_slow_endpoints_time_out() {
+    let endpoints = vec![
+        "http://www.endpoint0.com".to_string(),
+        "http://www.endpoint1.com".to_string(),
+        "http://www.endpoint2.com".to_string(),
+        "http://www.slow.com".to_string(),
+    ];
+    let mut config = sepolia_config(endpoints);
+    config.quorum = Some(QuorumPolicy::MinCount(3));
+    let checkpoint_client = CheckpointClient::new(SlowClient, StateId::Finalized, config)
+        .with_request_config(fast_retries(10));
+    let started = std::time::Instant::now();
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    // retries of the slow endpoint are cut short by the deadline
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(result.canonical.unwrap().contains_key("Hash1"));
+    assert_eq!(result.failure.len(), 1);
+    assert_eq!(result.failure[0].endpoint, "http://www.slow.com");
+    assert!(matches!(
+        result.failure[0].payload,
+        AppError::Timeout { .. }
+    ));
+}
+
+#[tokio::test]
+pub async fn test_early_exit_once_quorum_is_reached() {
+    let endpoints = vec![
+        "http://www.endpoint0.com".to_string(),
+        "http://www.endpoint1.com".to_string(),
+        "http://www.endpoint2.com".to_string(),
+        "http://www.slow.com".to_string(),
+    ];
+    let mut config = sepolia_config(endpoints);
+    config.quorum = Some(QuorumPolicy::MinCount(3));
+    let checkpoint_client = CheckpointClient::new(SlowClient, StateId::Finalized, config)
+        .with_request_config(RequestConfig {
+            deadline: Duration::from_secs(30),
+            ..fast_retries(0)
+        })
+        .with_early_exit(true);
+    let started = std::time::Instant::now();
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(result.canonical.unwrap().contains_key("Hash1"));
+    assert!(result.failure.is_empty());
+    assert_eq!(result.not_awaited, vec!["http://www.slow.com".to_string()]);
+}
+
+#[tokio::test]
+pub async fn test_early_exit_once_quorum_is_impossible() {
+    let endpoints = vec![
+        "http://www.endpoint0.com/root=Hash1".to_string(),
+        "http://www.endpoint1.com/root=Hash2".to_string(),
+        "http://www.endpoint2.com/root=Hash3".to_string(),
+        "http://www.slow.com".to_string(),
+    ];
+    let mut config = sepolia_config(endpoints);
+    config.quorum = Some(QuorumPolicy::MinCount(3));
+    let checkpoint_client = CheckpointClient::new(SlowClient, StateId::Finalized, config)
+        .with_request_config(RequestConfig {
+            deadline: Duration::from_secs(30),
+            ..fast_retries(0)
+        });
+    let options = FetchOptions {
+        early_exit: true,
+        ..checkpoint_client.default_options()
+    };
+    let started = std::time::Instant::now();
+    let result = checkpoint_client
+        .fetch_finality_checkpoints_with_options("sepolia", &options)
+        .await
+        .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(result.canonical.is_none());
+    assert_eq!(result.non_canonical.unwrap().len(), 3);
+    assert_eq!(result.not_awaited.len(), 1);
+}
+
+// answers every request with the given status and body
+struct BodyClient {
+    status: u16,
+    body: String,
+}
+
+#[async_trait]
+impl HttpClient for BodyClient {
+    async fn send_request(&self, path: String) -> Result<Response, AppError> {
+        if let Some(response) = sepolia_response(&path) {
+            return Ok(response);
+        }
+        let mut response = http::response::Response::new(self.body.clone());
+        *response.status_mut() = http::StatusCode::from_u16(self.status).unwrap();
+        Ok(Response::from(response))
+    }
+}
+
+#[tokio::test]
+pub async fn test_failures_are_categorized() {
+    let endpoints: Vec<String> = (0..3)
+        .map(|index| format!("http://www.endpoint{index}.com"))
+        .collect();
+    let failure_for = |status: u16, body: &str| {
+        let checkpoint_client = CheckpointClient::new(
+            BodyClient {
+                status,
+                body: body.to_string(),
+            },
+            StateId::Finalized,
+            sepolia_config(endpoints.clone()),
+        )
+        .with_request_config(fast_retries(0));
+        async move {
+            let result = checkpoint_client
+                .fetch_finality_checkpoints("sepolia")
+                .await
+                .unwrap();
+            serde_json::to_value(&result.failure[0]).unwrap()
+        }
+    };
+
+    let failure = failure_for(503, &"x".repeat(500)).await;
+    assert_eq!(failure["endpoint"], "http://www.endpoint0.com");
+    assert_eq!(failure["payload"]["kind"], "http_status");
+    assert_eq!(failure["payload"]["endpoint"], "http://www.endpoint0.com");
+    assert_eq!(failure["payload"]["code"], 503);
+    assert_eq!(failure["payload"]["body"].as_str().unwrap().len(), 200);
+
+    let failure = failure_for(200, "not json").await;
+    assert_eq!(failure["payload"]["kind"], "decode");
+
+    let failure = failure_for(200, r#"{"data": {}}"#).await;
+    assert_eq!(failure["payload"]["kind"], "invalid_payload");
+}
+
+async fn get_from_server<C: HttpClient + Send + Sync + 'static>(
+    checkpoint_client: CheckpointClient<C>,
+    uri: &str,
+) -> (http::StatusCode, serde_json::Value) {
+    get(
+        CheckPointMiddleware::new(checkpoint_client, 0).router(),
+        uri,
+    )
+    .await
+}
+
+async fn get(router: axum::Router, uri: &str) -> (http::StatusCode, serde_json::Value) {
+    let response = router
+        .oneshot(
+            http::Request::builder()
+                .uri(uri)
+                .body(hyper::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn server_client(roots: Vec<Result<&str, &str>>) -> CheckpointClient<MockClient> {
+    let (client, endpoints) = mocks_with_roots(roots);
+    CheckpointClient::new(client, StateId::Finalized, sepolia_config(endpoints))
+        .with_request_config(fast_retries(0))
+}
+
+#[tokio::test]
+pub async fn test_server_status_codes() {
+    let (status, body) = get_from_server(
+        server_client(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]),
+        "/sepolia/finalized",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["block_root"], "Hash1");
+    assert_eq!(body["status"], "canonical");
+
+    let (status, body) = get_from_server(
+        server_client(vec![Ok("Hash1"), Ok("Hash2"), Ok("Hash3")]),
+        "/sepolia/finalized",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CONFLICT);
+    assert_eq!(body["code"], "conflicting_checkpoints");
+    assert!(body.get("block_root").is_none());
+
+    let (status, body) = get_from_server(
+        server_client(vec![Ok("Hash1"), Err("down"), Err("down")]),
+        "/sepolia/finalized?verbose=true",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["code"], "insufficient_quorum");
+    assert_eq!(body["failure"].as_array().unwrap().len(), 2);
+
+    let (status, body) = get_from_server(
+        server_client(vec![Err("down"), Err("down"), Err("down")]),
+        "/sepolia/finalized",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["code"], "no_response");
+
+    let (status, body) = get_from_server(
+        server_client(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]),
+        "/holesky/finalized",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "unknown_network");
+
+    let (status, body) = get_from_server(
+        server_client(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]),
+        "/goerli/finalized",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "unknown_network");
+
+    let (status, body) = get_from_server(
+        server_client(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]),
+        "/sepolia/finalized?quorum=most",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_parameter");
+}
+
+#[tokio::test]
+pub async fn test_server_justified_reports_missing_roots_as_null() {
+    let (client, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]);
+    let client = client.with_current_justified(
+        endpoints
+            .iter()
+            .enumerate()
+            .map(|(index, endpoint)| (endpoint.clone(), format!("Justified{index}")))
+            .collect(),
+    );
+    let checkpoint_client =
+        CheckpointClient::new(client, StateId::Finalized, sepolia_config(endpoints));
+    let (status, body) = get_from_server(checkpoint_client, "/sepolia/justified").await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["finalized"]["block_root"], "Hash1");
+    assert_eq!(body["current_justified"]["status"], "conflicting");
+    assert!(body["current_justified"]["block_root"].is_null());
+}
+
+// answers with the given genesis validators root and fork version for the endpoints containing
+// their key, and with sepolia otherwise, counting the requests made per path
+struct ChainClient {
+    chains: Vec<(&'static str, &'static str, &'static str)>,
+    calls: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+#[async_trait]
+impl HttpClient for ChainClient {
+    async fn send_request(&self, path: String) -> Result<Response, AppError> {
+        *self.calls.lock().unwrap().entry(path.clone()).or_default() += 1;
+        let (_, genesis_validators_root, fork_version) = self
+            .chains
+            .iter()
+            .find(|(key, _, _)| path.contains(key))
+            .unwrap_or(&("", SEPOLIA_GENESIS_VALIDATORS_ROOT, SEPOLIA_FORK_VERSION));
+        Ok(chain_response(&path, genesis_validators_root, fork_version)
+            .unwrap_or_else(|| payload_response("Hash1")))
+    }
+}
+
+#[tokio::test]
+pub async fn test_providers_on_another_network_are_rejected() {
+    let endpoints: Vec<String> = ["good1", "good2", "mainnet", "forked"]
+        .iter()
+        .map(|name| format!("http://www.{name}.com"))
+        .collect();
+    let client = ChainClient {
+        chains: vec![
+            (
+                "mainnet",
+                "0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95",
+                "0x06000000",
+            ),
+            ("forked", SEPOLIA_GENESIS_VALIDATORS_ROOT, "0x12345678"),
+        ],
+        calls: Default::default(),
+    };
+    let checkpoint_client = CheckpointClient::new(
+        client,
+        StateId::Finalized,
+        sepolia_config(endpoints.clone()),
+    );
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    // the two providers on sepolia are not enough for quorum over the four configured
+    assert!(result.canonical.is_none());
+    assert_eq!(result.tally[0].weight, 2);
+    let failures: Vec<serde_json::Value> = result
+        .failure
+        .iter()
+        .map(|failure| serde_json::to_value(&failure.payload).unwrap())
+        .collect();
+    assert_eq!(failures.len(), 2);
+    assert!(failures
+        .iter()
+        .all(|failure| failure["kind"] == "network_mismatch"));
+    assert_eq!(failures[0]["endpoint"], "http://www.mainnet.com");
+    assert!(failures[0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("genesis validators root"));
+    assert_eq!(failures[1]["endpoint"], "http://www.forked.com");
+    assert!(failures[1]["message"]
+        .as_str()
+        .unwrap()
+        .contains("0x12345678"));
+
+    let client = ChainClient {
+        chains: vec![("forked", SEPOLIA_GENESIS_VALIDATORS_ROOT, "0x12345678")],
+        calls: Default::default(),
+    };
+    let result = CheckpointClient::new(client, StateId::Finalized, sepolia_config(endpoints))
+        .with_network_check(false)
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert!(result.failure.is_empty());
+}
+
+#[test]
+pub fn test_fork_version_schedule() {
+    let sepolia = EndpointsConfig::default().network("sepolia").unwrap();
+    assert_eq!(sepolia.fork_versions_at(0), vec!["0x90000069"]);
+    assert_eq!(sepolia.fork_versions_at(222463), vec!["0x90000073"]);
+    // the previous fork version is still accepted during the first epoch of a fork
+    assert_eq!(
+        sepolia.fork_versions_at(222464),
+        vec!["0x90000074", "0x90000073"]
+    );
+    assert_eq!(sepolia.fork_versions_at(222465), vec!["0x90000074"]);
+    assert!(sepolia.is_current_fork_version("0x90000075", 272640));
+    assert!(!sepolia.is_current_fork_version("0x90000074", 272641));
+    assert!(!sepolia.is_current_fork_version("0x90000069", 272641));
+
+    // without fork epochs only the last fork version is accepted
+    let devnet: Network = serde_yaml::from_str(&format!(
+        "genesis_validators_root: \"{SEPOLIA_GENESIS_VALIDATORS_ROOT}\"\ngenesis_time: 0\nfork_versions: [\"0x10000000\", \"0x20000000\"]"
+    ))
+    .unwrap();
+    assert!(devnet.is_current_fork_version("0x20000000", 0));
+    assert!(!devnet.is_current_fork_version("0x10000000", 0));
+}
+
+#[tokio::test]
+pub async fn test_stale_fork_version_is_rejected() {
+    // a provider stuck on the fork before the current one is not on the network
+    let endpoints: Vec<String> = ["good1", "good2", "stale"]
+        .iter()
+        .map(|name| format!("http://www.{name}.com"))
+        .collect();
+    let client = ChainClient {
+        chains: vec![("stale", SEPOLIA_GENESIS_VALIDATORS_ROOT, "0x90000074")],
+        calls: Default::default(),
+    };
+    let result = CheckpointClient::new(client, StateId::Finalized, sepolia_config(endpoints))
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    let failures: Vec<serde_json::Value> = result
+        .failure
+        .iter()
+        .map(|failure| serde_json::to_value(&failure.payload).unwrap())
+        .collect();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0]["kind"], "network_mismatch");
+    assert_eq!(failures[0]["endpoint"], "http://www.stale.com");
+    assert!(failures[0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("0x90000075"));
+}
+
+#[tokio::test]
+pub async fn test_provider_network_is_cached() {
+    let (_, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]);
+    let calls: Arc<Mutex<HashMap<String, usize>>> = Default::default();
+    let checkpoint_client = CheckpointClient::new(
+        ChainClient {
+            chains: vec![],
+            calls: calls.clone(),
+        },
+        StateId::Finalized,
+        sepolia_config(endpoints),
+    );
+    for _ in 0..2 {
+        let result = checkpoint_client
+            .fetch_finality_checkpoints("sepolia")
+            .await
+            .unwrap();
+        assert!(result.canonical.is_some());
+    }
+    let calls = calls.lock().unwrap().clone();
+    // genesis, fork, finality checkpoints and header for each endpoint
+    assert_eq!(calls.len(), 12);
+    for (path, count) in calls {
+        if path.ends_with("/genesis") || path.ends_with("/fork") {
+            assert_eq!(count, 1);
+        } else {
+            assert_eq!(count, 2);
+        }
+    }
+}
+
+// reports the endpoints containing "syncing" as syncing and optimistic, the ones containing
+// "offline" as having their execution layer offline, and the others as synced
+struct SyncClient;
+
+#[async_trait]
+impl HttpClient for SyncClient {
+    async fn send_request(&self, path: String) -> Result<Response, AppError> {
+        if let Some(response) = sepolia_response(&path) {
+            return Ok(response);
+        }
+        if !path.ends_with("/eth/v1/node/syncing") {
+            return Ok(payload_response("Hash1"));
+        }
+        let syncing = path.contains("syncing.com");
+        let payload = SyncingPayload {
+            data: SyncStatus {
+                head_slot: "100".to_string(),
+                sync_distance: if syncing { "50" } else { "0" }.to_string(),
+                is_syncing: syncing,
+                is_optimistic: syncing,
+                el_offline: path.contains("offline"),
+            },
+        };
+        Ok(Response::from(http::response::Response::new(
+            serde_json::to_string(&payload).unwrap(),
+        )))
+    }
+}
+
+#[tokio::test]
+pub async fn test_sync_check() {
+    let endpoints: Vec<String> = ["good1", "good2", "syncing", "offline"]
+        .iter()
+        .map(|name| format!("http://www.{name}.com"))
+        .collect();
+    let checkpoint_client =
+        CheckpointClient::new(SyncClient, StateId::Finalized, sepolia_config(endpoints));
+    let fetch = |sync_check: SyncCheck| {
+        let options = FetchOptions {
+            sync_check,
+            ..checkpoint_client.default_options()
+        };
+        let checkpoint_client = &checkpoint_client;
+        async move {
+            checkpoint_client
+                .fetch_finality_checkpoints_with_options("sepolia", &options)
+                .await
+                .unwrap()
+        }
+    };
+
+    let result = fetch(SyncCheck::Off).await;
+    assert!(result.canonical.is_some());
+    assert!(result.flagged.is_empty());
+
+    // flagged providers still vote
+    let result = fetch(SyncCheck::Flag).await;
+    assert_eq!(tally_for(&result, "Hash1").weight, 4);
+    assert_eq!(result.flagged.len(), 2);
+    assert_eq!(result.flagged[0].endpoint, "http://www.syncing.com");
+    assert_eq!(
+        result.flagged[0].issues,
+        vec![SyncIssue::Syncing, SyncIssue::Optimistic]
+    );
+    assert_eq!(result.flagged[1].endpoint, "http://www.offline.com");
+    assert_eq!(result.flagged[1].issues, vec![SyncIssue::ElOffline]);
+
+    // dropped providers are failures, leaving two votes out of four
+    let result = fetch(SyncCheck::Drop).await;
+    assert!(result.canonical.is_none());
+    assert!(result.flagged.is_empty());
+    assert_eq!(tally_for(&result, "Hash1").weight, 2);
+    let failure = serde_json::to_value(&result.failure[0].payload).unwrap();
+    assert_eq!(failure["kind"], "not_synced");
+    assert_eq!(failure["endpoint"], "http://www.syncing.com");
+    assert_eq!(
+        failure["issues"],
+        serde_json::json!(["syncing", "optimistic"])
+    );
+    assert_eq!("drop".parse::<SyncCheck>().unwrap(), SyncCheck::Drop);
+    assert!("sometimes".parse::<SyncCheck>().is_err());
+}
+
+// finalizes Hash1 at epoch 10, with the block at slot 320 unless the endpoint contains the key of
+// one of the given (key, slot, state root) overrides
+struct HeaderClient {
+    headers: Vec<(&'static str, &'static str, &'static str)>,
+}
+
+#[async_trait]
+impl HttpClient for HeaderClient {
+    async fn send_request(&self, path: String) -> Result<Response, AppError> {
+        if let Some(response) = sepolia_response(&path) {
+            return Ok(response);
+        }
+        if !path.ends_with("/eth/v1/beacon/headers/Hash1") {
+            return Ok(checkpoint_response("Hash1", "10"));
+        }
+        let (_, slot, state_root) = self
+            .headers
+            .iter()
+            .find(|(key, _, _)| path.contains(key))
+            .unwrap_or(&("", "320", "0xstate"));
+        let payload = HeaderPayload {
+            data: HeaderData {
+                root: "Hash1".to_string(),
+                header: SignedBeaconBlockHeader {
+                    message: BeaconBlockHeader {
+                        slot: slot.to_string(),
+                        proposer_index: "7".to_string(),
+                        parent_root: "0xparent".to_string(),
+                        state_root: state_root.to_string(),
+                        body_root: "0xbody".to_string(),
+                    },
+                    signature: "0xsignature".to_string(),
+                },
+            },
+        };
+        Ok(Response::from(http::response::Response::new(
+            serde_json::to_string(&payload).unwrap(),
+        )))
+    }
+}
+
+// a HeaderClient answering the finality checkpoints and the headers after the given delays
+struct SlowHeaderClient {
+    finality_delay: Duration,
+    header_delay: Duration,
+}
+
+#[async_trait]
+impl HttpClient for SlowHeaderClient {
+    async fn send_request(&self, path: String) -> Result<Response, AppError> {
+        if path.contains("finality_checkpoints") {
+            tokio::time::sleep(self.finality_delay).await;
+        } else if path.contains("/headers/") {
+            tokio::time::sleep(self.header_delay).await;
+        }
+        HeaderClient { headers: vec![] }.send_request(path).await
+    }
+}
+
+#[tokio::test]
+pub async fn test_header_verification_is_bounded_by_the_deadline() {
+    let (_, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]);
+    let checkpoint_client = CheckpointClient::new(
+        SlowHeaderClient {
+            finality_delay: Duration::from_millis(300),
+            header_delay: Duration::from_millis(350),
+        },
+        StateId::Finalized,
+        sepolia_config(endpoints),
+    )
+    .with_request_config(RequestConfig {
+        request_timeout: Duration::from_millis(400),
+        ..fast_retries(0)
+    });
+    let started = std::time::Instant::now();
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    // the headers would only arrive 650ms into the 500ms round
+    assert!(started.elapsed() < Duration::from_millis(600));
+    let header = result.header.unwrap();
+    assert!(header.headers.is_empty());
+    assert_eq!(header.failure.len(), 3);
+}
+
+#[tokio::test]
+pub async fn test_finalized_header_is_verified() {
+    let (_, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]);
+    let fetch = |headers: Vec<(&'static str, &'static str, &'static str)>| {
+        let checkpoint_client = CheckpointClient::new(
+            HeaderClient { headers },
+            StateId::Finalized,
+            sepolia_config(endpoints.clone()),
+        );
+        async move {
+            checkpoint_client
+                .fetch_finality_checkpoints("sepolia")
+                .await
+                .unwrap()
+        }
+    };
+
+    let result = fetch(vec![]).await;
+    assert_eq!(result.status(), QuorumStatus::Canonical);
+    let header = result.verified_header().unwrap();
+    assert_eq!(header.slot, "320");
+    assert_eq!(header.state_root, "0xstate");
+
+    // the first slot of the epoch is empty, the block is the last one before it
+    let result = fetch(vec![("endpoint", "300", "0xstate")]).await;
+    assert_eq!(result.status(), QuorumStatus::Canonical);
+    assert_eq!(result.verified_header().unwrap().slot, "300");
+
+    let result = fetch(vec![("endpoint2", "320", "0xother")]).await;
+    assert_eq!(result.status(), QuorumStatus::HeaderMismatch);
+    assert!(result.verified_header().is_none());
+    assert_eq!(result.header.unwrap().headers.len(), 3);
+
+    let result = fetch(vec![("endpoint", "321", "0xstate")]).await;
+    assert_eq!(result.status(), QuorumStatus::HeaderMismatch);
+    assert!(result
+        .header
+        .unwrap()
+        .mismatch
+        .unwrap()
+        .contains("not within epoch 10"));
+
+    // an epoch whose first slot does not fit in a u64 is reported, not computed
+    let checkpoint = BlockInfo {
+        epoch: u64::MAX.to_string(),
+        root: "Hash1".to_string(),
+    };
+    let payload = HeaderPayload {
+        data: HeaderData {
+            root: "Hash1".to_string(),
+            header: SignedBeaconBlockHeader {
+                message: BeaconBlockHeader {
+                    slot: "320".to_string(),
+                    proposer_index: "0".to_string(),
+                    parent_root: "0xparent".to_string(),
+                    state_root: "0xstate".to_string(),
+                    body_root: "0xbody".to_string(),
+                },
+                signature: "0xsignature".to_string(),
+            },
+        },
+    };
+    let verification = verify_headers(&checkpoint, 32, vec![("endpoint".to_string(), Ok(payload))]);
+    assert!(verification.header.is_none());
+    assert!(verification
+        .mismatch
+        .unwrap()
+        .starts_with("Invalid slot 320 or epoch"));
+
+    // the finalized slot and state root are part of the server response
+    let (status, body) = get_from_server(
+        CheckpointClient::new(
+            HeaderClient { headers: vec![] },
+            StateId::Finalized,
+            sepolia_config(endpoints.clone()),
+        ),
+        "/sepolia/finalized",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["slot"], "320");
+    assert_eq!(body["state_root"], "0xstate");
+
+    let (status, body) = get_from_server(
+        CheckpointClient::new(
+            HeaderClient {
+                headers: vec![("endpoint0", "320", "0xother")],
+            },
+            StateId::Finalized,
+            sepolia_config(endpoints),
+        ),
+        "/sepolia/finalized",
+    )
+    .await;
+    assert_eq!(status, http::StatusCode::CONFLICT);
+    assert_eq!(body["code"], "header_mismatch");
+}
+
+// serialization of a value of the type with every field zeroed and every list empty
+fn default_ssz(ssz_type: &SszType) -> Vec<u8> {
+    let serialize_all = |types: Vec<&SszType>| {
+        let fixed_length: usize = types
+            .iter()
+            .map(|element| element.fixed_size().unwrap_or(4))
+            .sum();
+        let (mut fixed, mut variable) = (vec![], vec![]);
+        for element in types {
+            let bytes = default_ssz(element);
+            if element.fixed_size().is_some() {
+                fixed.extend(bytes);
+            } else {
+                fixed.extend(((fixed_length + variable.len()) as u32).to_le_bytes());
+                variable.extend(bytes);
+            }
+        }
+        fixed.extend(variable);
+        fixed
+    };
+    match ssz_type {
+        SszType::Uint(size) | SszType::Bytes(size) => vec![0; *size],
+        SszType::Bitvector(length) => vec![0; length.div_ceil(8)],
+        SszType::ByteList(_) | SszType::List(_, _) => vec![],
+        SszType::Bitlist(_) => vec![1],
+        SszType::Vector(element, length) => serialize_all(vec![element.as_ref(); *length]),
+        SszType::Container(fields) => {
+            serialize_all(fields.iter().map(|(_, field)| field).collect())
+        }
+    }
+}
+
+// xorshift64, so the values generated from a seed never change
+struct Xorshift(u64);
+
+impl Xorshift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn bytes(&mut self, length: usize) -> Vec<u8> {
+        (0..length).map(|_| self.next() as u8).collect()
+    }
+}
+
+// serialization of a valid value of the type filled from the generator, with a few elements in
+// every list. uint8 are generated as booleans
+fn random_ssz(ssz_type: &SszType, rng: &mut Xorshift) -> Vec<u8> {
+    let serialize_all = |types: Vec<&SszType>, rng: &mut Xorshift| {
+        let fixed_length: usize = types
+            .iter()
+            .map(|element| element.fixed_size().unwrap_or(4))
+            .sum();
+        let (mut fixed, mut variable) = (vec![], vec![]);
+        for element in types {
+            let bytes = random_ssz(element, rng);
+            if element.fixed_size().is_some() {
+                fixed.extend(bytes);
+            } else {
+                fixed.extend(((fixed_length + variable.len()) as u32).to_le_bytes());
+                variable.extend(bytes);
+            }
+        }
+        fixed.extend(variable);
+        fixed
+    };
+    match ssz_type {
+        SszType::Uint(1) => vec![rng.below(2) as u8],
+        SszType::Uint(size) | SszType::Bytes(size) => rng.bytes(*size),
+        SszType::ByteList(limit) => {
+            let length = rng.below(*limit.min(&40) + 1);
+            rng.bytes(length)
+        }
+        SszType::Bitvector(length) => {
+            let mut bytes = rng.bytes(length.div_ceil(8));
+            if length % 8 != 0 {
+                *bytes.last_mut().unwrap() &= (1 << (length % 8)) - 1;
+            }
+            bytes
+        }
+        SszType::Bitlist(limit) => {
+            let length = rng.below(*limit.min(&300) + 1);
+            let mut bytes = rng.bytes(length / 8 + 1);
+            let last = bytes.last_mut().unwrap();
+            *last &= (1 << (length % 8)) - 1;
+            *last |= 1 << (length % 8);
+            bytes
+        }
+        SszType::Vector(element, length) => serialize_all(vec![element.as_ref(); *length], rng),
+        SszType::List(element, limit) => {
+            let length = rng.below(*limit.min(&3) + 1);
+            serialize_all(vec![element.as_ref(); length], rng)
+        }
+        SszType::Container(fields) => {
+            serialize_all(fields.iter().map(|(_, field)| field).collect(), rng)
+        }
+    }
+}
+
+fn sha256(left: &[u8], right: &[u8]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    Sha256::new()
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+#[test]
+pub fn test_hash_tree_root() {
+    let checkpoint = SszType::Container(vec![
+        ("epoch", SszType::Uint(8)),
+        ("root", SszType::Bytes(32)),
+    ]);
+    let mut bytes = 10u64.to_le_bytes().to_vec();
+    bytes.extend([7u8; 32]);
+    let mut epoch_chunk = [0u8; 32];
+    epoch_chunk[..8].copy_from_slice(&10u64.to_le_bytes());
+    assert_eq!(
+        checkpoint.hash_tree_root(&bytes).unwrap(),
+        sha256(&epoch_chunk, &[7u8; 32])
+    );
+    // a container is too short without its root
+    assert!(checkpoint.hash_tree_root(&bytes[..8]).is_err());
+
+    // a list of uint64 packs its elements into chunks, padded up to its limit, then mixes in its length
+    let list = SszType::list(SszType::Uint(8), 8);
+    let bytes: Vec<u8> = [1u64, 2, 3, 4, 5]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    let mut chunks = [[0u8; 32]; 2];
+    chunks[0].copy_from_slice(&bytes[..32]);
+    chunks[1][..8].copy_from_slice(&bytes[32..]);
+    let mut length = [0u8; 32];
+    length[0] = 5;
+    assert_eq!(
+        list.hash_tree_root(&bytes).unwrap(),
+        sha256(&sha256(&chunks[0], &chunks[1]), &length)
+    );
+    assert!(list
+        .hash_tree_root(&[bytes.clone(), bytes.clone()].concat())
+        .is_err());
+
+    // the delimiting bit of a bitlist is not part of its root
+    let bitlist = SszType::Bitlist(2048);
+    let mut bits = [0u8; 32];
+    bits[0] = 0b101;
+    let mut length = [0u8; 32];
+    length[0] = 3;
+    assert_eq!(
+        bitlist.hash_tree_root(&[0b1101]).unwrap(),
+        sha256(&merkleize(&[bits], 8), &length)
+    );
+    assert!(bitlist.hash_tree_root(&[0]).is_err());
+}
+
+// The roots were computed by the tree_hash crate over the consensus types of ethportal-api 0.12,
+// which decoded the same serializations and encoded them back unchanged. These types stop at
+// electra, and phase0 blocks are covered by the mainnet genesis block only
+#[test]
+pub fn test_known_answer_roots() {
+    let roots = [
+        (
+            ForkName::Bellatrix,
+            "0xa80b4a843e5c91e09f7119d9c6486f96980a61226678f4702659585f2dd34774",
+            "0xf919f1cddb51ed6e01059b8a8aa8247fdc1efd3d9bb62d4a6d4136a3c3dbca71",
+        ),
+        (
+            ForkName::Capella,
+            "0x89523f5d6420a6915bb8b5e7b9ad785f834644ffddfaf9eef73b9349d7fd70f0",
+            "0x08586d5b67d50a51b778c0b57e3aa415ac55fcd6c56c6569159cefba89b968d8",
+        ),
+        (
+            ForkName::Deneb,
+            "0x40adaa1a62aeeed1d7ad7b651561fd2366f08bc06016da3d971359f3989c583a",
+            "0xa202025f4497da2b2e28f2b0d9aef766aa469a805c472d9afc715ee14bb6628c",
+        ),
+        (
+            ForkName::Electra,
+            "0x0ae0a0b4036fdbd95d3599c59e9eff5bac68610c3e1b2ddf6da8b3097db65fe0",
+            "0xe987254fcdf8196e1100da955ea126def77a133dfb13911e6a9fb4a9fff2a366",
+        ),
+    ];
+    for (fork, state_root, block_root) in roots {
+        let state = random_ssz(&fork.beacon_state(), &mut Xorshift(1));
+        assert_eq!(
+            to_hex(&fork.beacon_state().hash_tree_root(&state).unwrap()),
+            state_root,
+            "{fork} state"
+        );
+        let block = random_ssz(&fork.beacon_block(), &mut Xorshift(1));
+        assert_eq!(
+            to_hex(&fork.beacon_block().hash_tree_root(&block).unwrap()),
+            block_root,
+            "{fork} block"
+        );
+    }
+
+    // the genesis block of mainnet has an empty body and the genesis state root
+    let fork = ForkName::Phase0;
+    let mut block = default_ssz(&fork.beacon_block());
+    block[48..80].copy_from_slice(
+        &hex::decode("7e76880eb67bbdc86250aa578958e9d0675e64e714337855204fb5abaaf82c2b").unwrap(),
+    );
+    assert_eq!(
+        to_hex(&fork.beacon_block().hash_tree_root(&block).unwrap()),
+        "0x4d611d5b93fdab69013a7f0a2f961caca0c853f87cfe9595fe50038163079360"
+    );
+}
+
+// serves a finalized fulu state and block, with the state of the endpoints containing one of the
+// corrupted keys changed after its root was taken
+struct SszClient {
+    corrupted: Vec<&'static str>,
+    block_root: String,
+    state_root: String,
+    state: Vec<u8>,
+    block: Vec<u8>,
+    // the state at the start of epoch 10, with the state root of the latest block header filled in
+    epoch_start_state: Vec<u8>,
+    epoch_start_state_root: String,
+}
+
+impl SszClient {
+    fn new(corrupted: Vec<&'static str>) -> Self {
+        let fork = ForkName::Fulu;
+        let body_root = fork
+            .beacon_block_body()
+            .hash_tree_root(&default_ssz(&fork.beacon_block_body()))
+            .unwrap();
+        let mut state = default_ssz(&fork.beacon_state());
+        // the slot follows genesis_time and genesis_validators_root, the fork version the slot and
+        // the previous version, and the latest block header the fork
+        state[40..48].copy_from_slice(&320u64.to_le_bytes());
+        state[52..56].copy_from_slice(&hex::decode("90000075").unwrap());
+        state[64..72].copy_from_slice(&320u64.to_le_bytes());
+        state[144..176].copy_from_slice(&body_root);
+        let state_root = fork.beacon_state().hash_tree_root(&state).unwrap();
+
+        let mut block = default_ssz(&fork.signed_beacon_block());
+        // the message follows its offset and the signature, its state root its slot, proposer
+        // index and parent root
+        let message = 4 + 96;
+        block[message..message + 8].copy_from_slice(&320u64.to_le_bytes());
+        block[message + 48..message + 80].copy_from_slice(&state_root);
+        let block_root = fork
+            .beacon_block()
+            .hash_tree_root(&block[message..])
+            .unwrap();
+
+        let mut epoch_start_state = state.clone();
+        epoch_start_state[112..144].copy_from_slice(&state_root);
+        let epoch_start_state_root = fork
+            .beacon_state()
+            .hash_tree_root(&epoch_start_state)
+            .unwrap();
+
+        Self {
+            corrupted,
+            block_root: to_hex(&block_root),
+            state_root: to_hex(&state_root),
+            state,
+            block,
+            epoch_start_state,
+            epoch_start_state_root: to_hex(&epoch_start_state_root),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpClient for SszClient {
+    async fn send_request(&self, path: String) -> Result<Response, AppError> {
+        if let Some(response) = sepolia_response(&path) {
+            return Ok(response);
+        }
+        if path.ends_with(&format!("/eth/v1/beacon/headers/{}", self.block_root)) {
+            let payload = HeaderPayload {
+                data: HeaderData {
+                    root: self.block_root.clone(),
+                    header: SignedBeaconBlockHeader {
+                        message: BeaconBlockHeader {
+                            slot: "320".to_string(),
+                            proposer_index: "0".to_string(),
+                            parent_root: to_hex(&[0; 32]),
+                            state_root: self.state_root.clone(),
+                            body_root: "0xbody".to_string(),
+                        },
+                        signature: "0xsignature".to_string(),
+                    },
+                },
+            };
+            return Ok(Response::from(http::response::Response::new(
+                serde_json::to_string(&payload).unwrap(),
+            )));
+        }
+        if path.ends_with("/eth/v1/beacon/states/320/root") {
+            let root = if self.corrupted.iter().any(|key| path.contains(key)) {
+                to_hex(&[1; 32])
+            } else {
+                self.epoch_start_state_root.clone()
+            };
+            let payload = serde_json::json!({ "data": { "root": root } });
+            return Ok(Response::from(http::response::Response::new(
+                payload.to_string(),
+            )));
+        }
+        if path.ends_with("/eth/v2/debug/beacon/states/320") {
+            let mut state = self.state.clone();
+            if self.corrupted.iter().any(|key| path.contains(key)) {
+                state[0] = 1;
+            }
+            return Ok(Response::from(http::response::Response::new(state)));
+        }
+        if path.ends_with(&format!("/eth/v2/beacon/blocks/{}", self.block_root)) {
+            return Ok(Response::from(http::response::Response::new(
+                self.block.clone(),
+            )));
+        }
+        Ok(checkpoint_response(&self.block_root, "10"))
+    }
+}
+
+#[tokio::test]
+pub async fn test_download_checkpoint() {
+    let (_, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]);
+    let download = |corrupted: Vec<&'static str>| {
+        let checkpoint_client = CheckpointClient::new(
+            SszClient::new(corrupted),
+            StateId::Finalized,
+            sepolia_config(endpoints.clone()),
+        );
+        async move {
+            let result = checkpoint_client
+                .fetch_finality_checkpoints("sepolia")
+                .await
+                .unwrap();
+            assert_eq!(result.status(), QuorumStatus::Canonical);
+            download_checkpoint(
+                &checkpoint_client,
+                "sepolia",
+                &result,
+                Duration::from_secs(10),
+            )
+            .await
+            .unwrap()
+        }
+    };
+
+    let expected = SszClient::new(vec![]);
+    let outcome = download(vec![]).await;
+    assert!(outcome.failure.is_empty());
+    let checkpoint = outcome.checkpoint.unwrap();
+    assert_eq!(checkpoint.fork, ForkName::Fulu);
+    assert_eq!(checkpoint.slot, "320");
+    assert_eq!(checkpoint.block_root, expected.block_root);
+    assert_eq!(checkpoint.state_root, expected.state_root);
+    assert_eq!(checkpoint.state, expected.state);
+    assert_eq!(checkpoint.block, expected.block);
+
+    // the provider whose state does not hash to the state root is skipped
+    let outcome = download(vec!["endpoint0"]).await;
+    let checkpoint = outcome.checkpoint.unwrap();
+    assert!(!checkpoint.endpoint.contains("endpoint0"));
+    assert_eq!(checkpoint.state, expected.state);
+    for failure in outcome.failure {
+        assert!(failure.endpoint.contains("endpoint0"));
+        assert!(matches!(failure.payload, AppError::RootMismatch { .. }));
+    }
+
+    let outcome = download(vec!["endpoint"]).await;
+    assert!(outcome.checkpoint.is_none());
+    assert_eq!(outcome.failure.len(), 3);
+    assert!(outcome
+        .failure
+        .iter()
+        .all(|failure| matches!(failure.payload, AppError::RootMismatch { .. })));
+}
+
+#[tokio::test]
+pub async fn test_download_requires_verified_checkpoint() {
+    let (_, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]);
+    let checkpoint_client = CheckpointClient::new(
+        HeaderClient {
+            headers: vec![("endpoint0", "320", "0xother")],
+        },
+        StateId::Finalized,
+        sepolia_config(endpoints),
+    );
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    let error = download_checkpoint(
+        &checkpoint_client,
+        "sepolia",
+        &result,
+        Duration::from_secs(10),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(error, AppError::NoVerifiedCheckpoint { .. }));
+}
+
+#[tokio::test]
+pub async fn test_verify_state() {
+    let (_, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]);
+    let checkpoint_client = CheckpointClient::new(
+        SszClient::new(vec![]),
+        StateId::Finalized,
+        sepolia_config(endpoints.clone()),
+    );
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    let network = checkpoint_client.network("sepolia").unwrap();
+    let expected = SszClient::new(vec![]);
+
+    assert!(is_block_state(&network, &expected.state).unwrap());
+    let verification = verify_state(&network, &result, &expected.state, None).unwrap();
+    assert_eq!(verification.fork, ForkName::Fulu);
+    assert_eq!(verification.slot, 320);
+    assert_eq!(verification.state_root, expected.state_root);
+    assert_eq!(verification.block_root, expected.block_root);
+
+    // once the next slot is processed, the state root is filled in the latest block header, and
+    // the state is checked against the state root the providers agree on
+    let state = expected.epoch_start_state.clone();
+    assert!(!is_block_state(&network, &state).unwrap());
+    assert_eq!(epoch_start_slot(&network, &result).unwrap(), 320);
+    let epoch_start_state_root = checkpoint_client
+        .fetch_state_root(&result, 320)
+        .await
+        .unwrap();
+    let verification =
+        verify_state(&network, &result, &state, Some(&epoch_start_state_root)).unwrap();
+    assert_eq!(verification.block_root, expected.block_root);
+    assert_eq!(verification.state_root, epoch_start_state_root);
+    assert!(matches!(
+        verify_state(&network, &result, &state, None),
+        Err(AppError::StateMismatch { .. })
+    ));
+
+    // an operator votes once on the state root, and enough distinct operators must agree on it
+    let mut one_operator = result.clone();
+    one_operator
+        .canonical
+        .iter_mut()
+        .flat_map(|canonical| canonical.values_mut().flatten())
+        .for_each(|value| value.operator = "operator".to_string());
+    assert!(matches!(
+        checkpoint_client.fetch_state_root(&one_operator, 320).await,
+        Err(AppError::NoVerifiedCheckpoint { .. })
+    ));
+    let mut too_few_operators = result.clone();
+    too_few_operators.threshold = 1;
+    too_few_operators.min_distinct_operators = 4;
+    assert!(matches!(
+        checkpoint_client
+            .fetch_state_root(&too_few_operators, 320)
+            .await,
+        Err(AppError::NoVerifiedCheckpoint { .. })
+    ));
+
+    // a forged state with the right latest block header
+    let mut forged = state.clone();
+    forged[0] = 1;
+    assert!(matches!(
+        verify_state(&network, &result, &forged, Some(&epoch_start_state_root)),
+        Err(AppError::StateMismatch { .. })
+    ));
+
+    // the state is neither the state of the block nor of the first slot of the epoch
+    let mut state = state.clone();
+    state[40..48].copy_from_slice(&321u64.to_le_bytes());
+    assert!(matches!(
+        verify_state(&network, &result, &state, Some(&epoch_start_state_root)),
+        Err(AppError::StateMismatch { .. })
+    ));
+
+    let mut state = expected.state.clone();
+    state[0] = 1;
+    assert!(matches!(
+        verify_state(&network, &result, &state, None),
+        Err(AppError::StateMismatch { .. })
+    ));
+
+    // a state of another fork
+    let mut state = expected.state.clone();
+    state[52..56].copy_from_slice(&hex::decode("00000000").unwrap());
+    assert!(matches!(
+        verify_state(&network, &result, &state, None),
+        Err(AppError::UnsupportedFork { .. })
+    ));
+    assert!(verify_state(&network, &result, &state[..40], None).is_err());
+
+    // providers that disagree on the state root at the start of the epoch
+    let checkpoint_client = CheckpointClient::new(
+        SszClient::new(vec!["endpoint1"]),
+        StateId::Finalized,
+        sepolia_config(endpoints),
+    );
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    assert!(matches!(
+        checkpoint_client.fetch_state_root(&result, 320).await,
+        Err(AppError::NoVerifiedCheckpoint { .. })
+    ));
+}
+
+#[tokio::test]
+pub async fn test_ws_checkpoint() {
+    let fetch = |roots: Vec<Result<&str, &str>>| {
+        let (mock, endpoints) = mocks_with_roots(roots);
+        let checkpoint_client =
+            CheckpointClient::new(mock, StateId::Finalized, sepolia_config(endpoints));
+        async move {
+            checkpoint_client
+                .fetch_finality_checkpoints("sepolia")
+                .await
+                .unwrap()
+        }
+    };
+
+    let result = fetch(vec![
+        Ok("0xabc:10"),
+        Err("down"),
+        Ok("0xabc:10"),
+        Ok("0xabc:10"),
+    ])
+    .await;
+    let ws_checkpoint = WsCheckpoint::from_result(&result).unwrap();
+    assert_eq!(ws_checkpoint.to_string(), "0xabc:10");
+    assert_ne!(ws_checkpoint.endpoint, "http://www.endpoint1.com");
+
+    let ws_checkpoint = WsCheckpoint {
+        root: "0xabc".to_string(),
+        epoch: "10".to_string(),
+        endpoint: "http://provider".to_string(),
+    };
+    assert_eq!(
+        BeaconClient::Lighthouse.flags(&ws_checkpoint),
+        vec![
+            "--checkpoint-sync-url=http://provider",
+            "--wss-checkpoint=0xabc:10"
+        ]
+    );
+    assert_eq!(
+        BeaconClient::Prysm.flags(&ws_checkpoint),
+        vec![
+            "--checkpoint-sync-url=http://provider",
+            "--genesis-beacon-api-url=http://provider",
+            "--weak-subjectivity-checkpoint=0xabc:10"
+        ]
+    );
+    assert_eq!(
+        BeaconClient::Teku.flags(&ws_checkpoint),
+        vec![
+            "--checkpoint-sync-url=http://provider",
+            "--ws-checkpoint=0xabc:10"
+        ]
+    );
+    assert_eq!(
+        BeaconClient::Nimbus.flags(&ws_checkpoint),
+        vec![
+            "--external-beacon-api-url=http://provider",
+            "--trusted-block-root=0xabc",
+            "--weak-subjectivity-checkpoint=0xabc:10"
+        ]
+    );
+    assert_eq!(
+        BeaconClient::Lodestar.flags(&ws_checkpoint),
+        vec![
+            "--checkpointSyncUrl=http://provider",
+            "--wssCheckpoint=0xabc:10"
+        ]
+    );
+    assert_eq!("Teku".parse::<BeaconClient>().unwrap(), BeaconClient::Teku);
+    assert!("geth".parse::<BeaconClient>().is_err());
+
+    // there is no weak subjectivity checkpoint without quorum
+    let result = fetch(vec![Ok("0xabc:10"), Ok("0xdef:10"), Err("down")]).await;
+    assert!(matches!(
+        WsCheckpoint::from_result(&result),
+        Err(AppError::NoVerifiedCheckpoint { .. })
+    ));
+}
+
+#[tokio::test]
+pub async fn test_report() {
+    let (mock, endpoints) = mocks_with_roots(vec![
+        Ok("0xabc:10"),
+        Ok("0xabc:10"),
+        Ok("0xabc:10"),
+        Err("down"),
+    ]);
+    let checkpoint_client =
+        CheckpointClient::new(mock, StateId::Finalized, sepolia_config(endpoints));
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    let verdict = result.verdict();
+    assert_eq!(verdict.status, QuorumStatus::Canonical);
+    assert_eq!(verdict.block_root.as_deref(), Some("0xabc"));
+    assert_eq!(verdict.epoch.as_deref(), Some("10"));
+
+    // the table lines up the verdict, then the tally
+    assert_eq!(
+        table(std::slice::from_ref(&result)),
+        [
+            "CHECKPOINT  STATUS     BLOCK ROOT  EPOCH  SLOT  STATE ROOT",
+            "finalized   canonical  0xabc       10     -     -",
+            "",
+            "CHECKPOINT  ROOT   EPOCH  WEIGHT  OPERATORS",
+            "finalized   0xabc  10     3       3",
+        ]
+        .join("\n")
+    );
+
+    // the verdict is at the top level, with the full result next to it
+    let report = serde_json::to_value(Report::from(result)).unwrap();
+    assert_eq!(report["status"], "canonical");
+    assert_eq!(report["block_root"], "0xabc");
+    assert_eq!(report["result"]["checkpoint"], "finalized");
+    assert_eq!(
+        report["result"]["canonical"]["0xabc"]
+            .as_array()
+            .unwrap()
+            .len(),
+        3
+    );
+    assert_eq!(
+        report["result"]["failure"][0]["endpoint"],
+        "http://www.endpoint3.com"
+    );
+
+    let yaml: serde_yaml::Value =
+        serde_yaml::from_str(&serde_yaml::to_string(&report).unwrap()).unwrap();
+    assert_eq!(yaml["status"].as_str(), Some("canonical"));
+
+    assert_eq!("JSON".parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+    assert_eq!("yaml".parse::<OutputFormat>().unwrap(), OutputFormat::Yaml);
+    assert_eq!(
+        "plain".parse::<OutputFormat>().unwrap(),
+        OutputFormat::Plain
+    );
+    assert_eq!(
+        "table".parse::<OutputFormat>().unwrap(),
+        OutputFormat::Table
+    );
+    assert_eq!(OutputFormat::default(), OutputFormat::Text);
+    assert!("xml".parse::<OutputFormat>().is_err());
+}
+
+#[test]
+pub fn test_exit_status() {
+    let exit_code = |status: QuorumStatus| ExitStatus::from(status) as u8;
+    assert_eq!(exit_code(QuorumStatus::Canonical), 0);
+    assert_eq!(exit_code(QuorumStatus::Conflicting), 3);
+    assert_eq!(exit_code(QuorumStatus::HeaderMismatch), 3);
+    assert_eq!(exit_code(QuorumStatus::Insufficient), 4);
+    assert_eq!(exit_code(QuorumStatus::Failed), 4);
+
+    let message = "message".to_string();
+    assert_eq!(
+        ExitStatus::from(&AppError::UnknownNetwork {
+            message: message.clone()
+        }),
+        ExitStatus::Configuration
+    );
+    assert_eq!(
+        ExitStatus::from(&AppError::InvalidConfig {
+            message: message.clone()
+        }),
+        ExitStatus::Configuration
+    );
+    assert_eq!(
+        ExitStatus::from(&AppError::StateMismatch { message }),
+        ExitStatus::Failure
+    );
+    assert_eq!(ExitStatus::Configuration as u8, 2);
+    assert_eq!(ExitStatus::Failure as u8, 1);
+}
+
+#[tokio::test]
+pub async fn test_watcher() {
+    let fetch = |roots: Vec<Result<&str, &str>>| {
+        let (mock, endpoints) = mocks_with_roots(roots);
+        let checkpoint_client =
+            CheckpointClient::new(mock, StateId::Finalized, sepolia_config(endpoints));
+        async move {
+            checkpoint_client
+                .fetch_finality_checkpoints("sepolia")
+                .await
+                .unwrap()
+        }
+    };
+    let finalized = |root: &'static str| fetch(vec![Ok(root), Ok(root), Ok(root)]);
+    let mut watcher = Watcher::new(4);
+
+    assert_eq!(
+        watcher.observe(&finalized("0xa:10").await, 12),
+        vec![WatchEvent::Finalized {
+            root: "0xa".to_string(),
+            epoch: 10
+        }]
+    );
+    assert!(watcher.observe(&finalized("0xa:10").await, 12).is_empty());
+    assert_eq!(
+        watcher.observe(&finalized("0xb:11").await, 13),
+        vec![WatchEvent::Finalized {
+            root: "0xb".to_string(),
+            epoch: 11
+        }]
+    );
+    assert_eq!(
+        watcher.observe(&finalized("0xc:11").await, 13),
+        vec![WatchEvent::Replaced {
+            root: "0xc".to_string(),
+            epoch: 11,
+            previous_root: "0xb".to_string()
+        }]
+    );
+    assert_eq!(
+        watcher.observe(&finalized("0xd:9").await, 13),
+        vec![WatchEvent::Regressed {
+            root: "0xd".to_string(),
+            epoch: 9,
+            previous_root: "0xc".to_string(),
+            previous_epoch: 11
+        }]
+    );
+
+    let events = watcher
+        .observe(
+            &fetch(vec![Ok("0xe:12"), Ok("0xf:12"), Err("down")]).await,
+            14,
+        )
+        .pop()
+        .unwrap();
+    let WatchEvent::NoQuorum { status, mut roots } = events else {
+        panic!("expected no quorum, got {events:?}");
+    };
+    roots.sort();
+    assert_eq!(status, QuorumStatus::Conflicting);
+    assert_eq!(roots, vec!["0xe", "0xf"]);
+
+    // finality did not move since the regression, and now lags the wall clock
+    assert_eq!(
+        watcher.observe(&finalized("0xd:9").await, 14),
+        vec![WatchEvent::Stalled {
+            epoch: 9,
+            current_epoch: 14
+        }]
+    );
+}
+
+#[test]
+pub fn test_epoch_start() {
+    let network = Network {
+        genesis_validators_root: SEPOLIA_GENESIS_VALIDATORS_ROOT.to_string(),
+        genesis_time: 100,
+        seconds_per_slot: 12,
+        slots_per_epoch: 32,
+        fork_versions: vec![],
+        fork_epochs: vec![],
+    };
+    assert_eq!(network.seconds_per_epoch(), 384);
+    assert_eq!(
+        network.epoch_start(2),
+        std::time::UNIX_EPOCH + Duration::from_secs(100 + 768)
+    );
+}
+
+#[tokio::test]
+pub async fn test_server_cache() {
+    let (_, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]);
+    let calls = Arc::new(Mutex::new(HashMap::new()));
+    let finality_calls = |calls: &Arc<Mutex<HashMap<String, usize>>>| -> usize {
+        calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path.contains("finality_checkpoints"))
+            .map(|(_, count)| count)
+            .sum()
+    };
+    let middleware = |cache_enabled: bool| {
+        Arc::new(
+            CheckPointMiddleware::new(
+                CheckpointClient::new(
+                    ChainClient {
+                        chains: vec![],
+                        calls: calls.clone(),
+                    },
+                    StateId::Finalized,
+                    sepolia_config(endpoints.clone()),
+                ),
+                0,
+            )
+            .with_cache(cache_enabled),
+        )
+    };
+
+    let cached = middleware(true);
+    let router = CheckPointMiddleware::routes(cached.clone());
+    let (status, body) = get(router.clone(), "/sepolia/finalized").await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["block_root"], "Hash1");
+    assert_eq!(body["age"], 0);
+    assert!(body["last_successful_refresh"].as_u64().unwrap() > 0);
+    assert_eq!(finality_calls(&calls), 3);
+
+    // served from the cache
+    let (status, body) = get(router.clone(), "/sepolia/finalized").await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["block_root"], "Hash1");
+    assert_eq!(finality_calls(&calls), 3);
+
+    // another state id has its own entry, and overriding other options skips the cache
+    get(router.clone(), "/sepolia/finalized?state_id=head").await;
+    assert_eq!(finality_calls(&calls), 6);
+    get(router.clone(), "/sepolia/finalized?state_id=head").await;
+    assert_eq!(finality_calls(&calls), 6);
+    let (_, body) = get(router.clone(), "/sepolia/finalized?quorum=min:2").await;
+    assert_eq!(finality_calls(&calls), 9);
+    assert!(body.get("age").is_none());
+
+    // slots and roots are never cached, nor refreshed in the background
+    let (_, body) = get(router.clone(), "/sepolia/finalized?state_id=12345").await;
+    assert_eq!(finality_calls(&calls), 12);
+    assert!(body.get("age").is_none());
+    get(router.clone(), "/sepolia/finalized?state_id=12345").await;
+    assert_eq!(finality_calls(&calls), 15);
+    cached
+        .refresh("sepolia", &StateId::Slot(12345))
+        .await
+        .unwrap();
+    assert_eq!(finality_calls(&calls), 18);
+    get(router.clone(), "/sepolia/finalized?state_id=12345").await;
+    assert_eq!(finality_calls(&calls), 21);
+
+    // the background refresh fetches again, and the next request is served from its result
+    cached
+        .refresh("sepolia", &StateId::Finalized)
+        .await
+        .unwrap();
+    assert_eq!(finality_calls(&calls), 24);
+    get(router, "/sepolia/finalized").await;
+    assert_eq!(finality_calls(&calls), 24);
+
+    let router = CheckPointMiddleware::routes(middleware(false));
+    get(router.clone(), "/sepolia/finalized").await;
+    let (_, body) = get(router, "/sepolia/finalized").await;
+    assert_eq!(finality_calls(&calls), 30);
+    assert!(body.get("age").is_none());
+}
+
+// answers like the providers of the chain client, after a delay so concurrent requests overlap
+struct DelayedClient {
+    calls: Arc<Mutex<HashMap<String, usize>>>,
+    delay: Duration,
+}
+
+#[async_trait]
+impl HttpClient for DelayedClient {
+    async fn send_request(&self, path: String) -> Result<Response, AppError> {
+        *self.calls.lock().unwrap().entry(path.clone()).or_default() += 1;
+        tokio::time::sleep(self.delay).await;
+        Ok(sepolia_response(&path).unwrap_or_else(|| payload_response("Hash1")))
+    }
+}
+
+#[tokio::test]
+pub async fn test_concurrent_requests_are_coalesced() {
+    let (_, endpoints) = mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]);
+    let calls = Arc::new(Mutex::new(HashMap::<String, usize>::new()));
+    let finality_calls = || -> usize {
+        calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path.contains("finality_checkpoints"))
+            .map(|(_, count)| count)
+            .sum()
+    };
+    let router = CheckPointMiddleware::new(
+        CheckpointClient::new(
+            DelayedClient {
+                calls: calls.clone(),
+                delay: Duration::from_millis(100),
+            },
+            StateId::Finalized,
+            sepolia_config(endpoints),
+        ),
+        0,
+    )
+    .with_cache(false)
+    .router();
+    let concurrently = |uris: Vec<&'static str>| {
+        futures::future::join_all(uris.into_iter().map(|uri| get(router.clone(), uri)))
+    };
+
+    // a single fan out to the three providers for all the identical requests
+    let responses = concurrently(vec!["/sepolia/finalized"; 10]).await;
+    assert!(responses.iter().all(|(status, body)| {
+        *status == http::StatusCode::OK && body["block_root"] == "Hash1"
+    }));
+    assert_eq!(finality_calls(), 3);
+
+    // requests with another state id or policy are not joined with each other
+    concurrently(vec![
+        "/sepolia/finalized",
+        "/sepolia/finalized",
+        "/sepolia/finalized?state_id=head",
+        "/sepolia/finalized?state_id=head",
+        "/sepolia/finalized?quorum=min:2",
+        "/sepolia/finalized?quorum=min:2",
+    ])
+    .await;
+    assert_eq!(finality_calls(), 12);
+
+    // once the fetch completes, the next request fans out again
+    get(router.clone(), "/sepolia/finalized").await;
+    assert_eq!(finality_calls(), 15);
+
+    // a fetch every request gave up on still completes, and is not joined afterwards
+    let abandoned = tokio::time::timeout(
+        Duration::from_millis(20),
+        get(router.clone(), "/sepolia/finalized"),
+    )
+    .await;
+    assert!(abandoned.is_err());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(finality_calls(), 18);
+    let (status, _) = get(router.clone(), "/sepolia/finalized").await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(finality_calls(), 21);
+}
+
+async fn get_text(router: axum::Router, uri: &str) -> (http::StatusCode, String) {
+    let response = router
+        .oneshot(
+            http::Request::builder()
+                .uri(uri)
+                .body(hyper::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+pub async fn test_metrics() {
+    let router = CheckPointMiddleware::new(
+        server_client(vec![
+            Ok("Hash1:10"),
+            Ok("Hash1:10"),
+            Ok("Hash1:10"),
+            Ok("Hash1:10"),
+            Ok("Hash1:10"),
+            Ok("Hash1:10"),
+            Ok("Hash2:10"),
+            Err("down"),
+        ]),
+        0,
+    )
+    .router();
+    get(router.clone(), "/sepolia/finalized").await;
+    get(router.clone(), "/sepolia/finalized").await;
+    // no quorum is unanimous, but the gauges only describe the default options
+    let (status, _) = get(router.clone(), "/sepolia/finalized?quorum=unanimous").await;
+    assert_eq!(status, http::StatusCode::CONFLICT);
+
+    let (status, body) = get_text(router, "/metrics").await;
+    assert_eq!(status, http::StatusCode::OK);
+    let metric = |name: &str| -> Vec<&str> {
+        body.lines()
+            .filter(|line| line.starts_with(&format!("checkpointq_{name}")))
+            .collect()
+    };
+    assert!(metric("provider_requests_total{")
+        .iter()
+        .any(|line| line.contains("resource=\"finality_checkpoints\"")));
+    assert!(!metric("provider_request_duration_seconds_bucket").is_empty());
+    assert_eq!(
+        metric("provider_failures_total"),
+        vec![
+            "checkpointq_provider_failures_total{endpoint=\"http://www.endpoint7.com\",kind=\"connect\",network=\"sepolia\"} 2"
+        ]
+    );
+    assert_eq!(
+        metric("agreement_ratio"),
+        vec!["checkpointq_agreement_ratio{network=\"sepolia\"} 0.75"]
+    );
+    assert_eq!(
+        metric("finalized_epoch"),
+        vec!["checkpointq_finalized_epoch{network=\"sepolia\"} 10"]
+    );
+    assert_eq!(
+        metric("conflicting_roots"),
+        vec!["checkpointq_conflicting_roots{network=\"sepolia\"} 1"]
+    );
+    assert_eq!(
+        metric("cache_hits_total"),
+        vec!["checkpointq_cache_hits_total{network=\"sepolia\"} 1"]
+    );
+    assert_eq!(
+        metric("cache_misses_total"),
+        vec!["checkpointq_cache_misses_total{network=\"sepolia\"} 1"]
+    );
+}
+
+#[tokio::test]
+pub async fn test_agreement_ratio_follows_the_quorum_weights() {
+    let (mock, endpoints) =
+        mocks_with_roots(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash2"), Err("down")]);
+    let mut config = sepolia_config(endpoints);
+    for (endpoint, (weight, operator)) in config
+        .endpoints
+        .get_mut("sepolia")
+        .unwrap()
+        .iter_mut()
+        .zip([(1, Some("acme")), (1, Some("acme")), (1, None), (2, None)])
+    {
+        endpoint.weight = weight;
+        endpoint.operator = operator.map(String::from);
+    }
+    let checkpoint_client = CheckpointClient::new(mock, StateId::Finalized, config)
+        .with_request_config(fast_retries(0));
+    let result = checkpoint_client
+        .fetch_finality_checkpoints("sepolia")
+        .await
+        .unwrap();
+    // acme votes once for Hash1, out of the weight of 4 the quorum policy was applied to
+    assert_eq!(result.configured_weight, 4);
+    let metrics = checkpoint_client.metrics().encode().unwrap();
+    assert!(metrics.contains("checkpointq_agreement_ratio{network=\"sepolia\"} 0.25"));
+}
+
+#[tokio::test]
+pub async fn test_health_and_ready() {
+    let router = CheckPointMiddleware::new(
+        server_client(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]),
+        0,
+    )
+    .router();
+    let (status, body) = get(router.clone(), "/health").await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["status"], "ok");
+
+    let (status, body) = get(router.clone(), "/ready").await;
+    assert_eq!(status, http::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["ready"], false);
+    assert_eq!(body["networks"]["sepolia"]["ready"], false);
+    assert!(body["networks"]["sepolia"]["age"].is_null());
+
+    // results fetched with other options do not count
+    get(router.clone(), "/sepolia/finalized?quorum=min:1").await;
+    let (status, _) = get(router.clone(), "/ready").await;
+    assert_eq!(status, http::StatusCode::SERVICE_UNAVAILABLE);
+
+    get(router.clone(), "/sepolia/finalized").await;
+    let (status, body) = get(router, "/ready").await;
+    assert_eq!(status, http::StatusCode::OK);
+    assert_eq!(body["ready"], true);
+    assert_eq!(body["networks"]["sepolia"]["age"], 0);
+
+    // a result older than the bound does not count
+    let middleware = Arc::new(
+        CheckPointMiddleware::new(
+            server_client(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]),
+            0,
+        )
+        .with_ready_max_age(Some(Duration::ZERO)),
+    );
+    middleware
+        .refresh("sepolia", &StateId::Finalized)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let readiness = middleware.readiness();
+    assert!(!readiness.ready);
+    assert_eq!(readiness.networks["sepolia"].age, Some(0));
+
+    // neither does one without quorum
+    let router = CheckPointMiddleware::new(
+        server_client(vec![Ok("Hash1"), Ok("Hash2"), Ok("Hash3")]),
+        0,
+    )
+    .router();
+    get(router.clone(), "/sepolia/finalized").await;
+    let (status, _) = get(router, "/ready").await;
+    assert_eq!(status, http::StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[test]
+pub fn test_bind_address() {
+    let parse = |s: &str| s.parse::<BindAddress>();
+    assert_eq!(
+        parse("0.0.0.0").unwrap().socket_addr(7070).to_string(),
+        "0.0.0.0:7070"
+    );
+    assert_eq!(
+        parse("::").unwrap().socket_addr(7070).to_string(),
+        "[::]:7070"
+    );
+    assert_eq!(
+        parse("[::1]").unwrap().socket_addr(7070).to_string(),
+        "[::1]:7070"
+    );
+    assert_eq!(
+        parse("[::1]:8080").unwrap().socket_addr(7070).to_string(),
+        "[::1]:8080"
+    );
+    assert_eq!(parse("10.0.0.1:8080").unwrap().to_string(), "10.0.0.1:8080");
+    assert_eq!(
+        BindAddress::default().socket_addr(7070).to_string(),
+        "127.0.0.1:7070"
+    );
+    assert!(matches!(
+        parse("localhost"),
+        Err(AppError::InvalidBindAddress { .. })
+    ));
+    assert!(matches!(
+        parse("10.0.0.1:port"),
+        Err(AppError::InvalidBindAddress { .. })
+    ));
+    assert_eq!(
+        ExitStatus::from(&parse("localhost").unwrap_err()),
+        ExitStatus::Configuration
+    );
+}
+
+// sends a HTTP/1.0 request, which the server answers before closing the connection
+async fn raw_request<S>(mut stream: S, path: &str) -> String
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    stream
+        .write_all(format!("GET {path} HTTP/1.0\r\n\r\n").as_bytes())
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[cfg(unix)]
+#[tokio::test]
+pub async fn test_server_listeners() {
+    let free_port = || {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    };
+    let (api, admin) = (free_port(), free_port());
+    let socket = std::env::temp_dir().join(format!("checkpointq-{}.sock", std::process::id()));
+    let server = CheckPointMiddleware::new(
+        server_client(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]),
+        0,
+    )
+    .with_listeners(vec![Listener::Tcp(api), Listener::Unix(socket.clone())])
+    .with_admin_listeners(vec![Listener::Tcp(admin)]);
+    tokio::spawn(server.serve());
+
+    let tcp = |addr| async move {
+        for _ in 0..100 {
+            if let Ok(stream) = tokio::net::TcpStream::connect(addr).await {
+                return stream;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("nothing listens on {addr}");
+    };
+    let unix = || async {
+        for _ in 0..100 {
+            if let Ok(stream) = tokio::net::UnixStream::connect(&socket).await {
+                return stream;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("nothing listens on {}", socket.display());
+    };
+
+    // the API listeners do not serve the admin routes, and the other way around
+    let response = raw_request(tcp(api).await, "/sepolia/finalized").await;
+    assert!(response.starts_with("HTTP/1.0 200"));
+    assert!(response.contains("\"block_root\":\"Hash1\""));
+    let response = raw_request(tcp(api).await, "/metrics").await;
+    assert!(response.starts_with("HTTP/1.0 404"));
+    let response = raw_request(unix().await, "/sepolia/finalized").await;
+    assert!(response.starts_with("HTTP/1.0 200"));
+    let response = raw_request(unix().await, "/health").await;
+    assert!(response.starts_with("HTTP/1.0 404"));
+
+    let response = raw_request(tcp(admin).await, "/health").await;
+    assert!(response.starts_with("HTTP/1.0 200"));
+    let response = raw_request(tcp(admin).await, "/metrics").await;
+    assert!(response.contains("checkpointq_provider_requests_total"));
+    let response = raw_request(tcp(admin).await, "/sepolia/finalized").await;
+    assert!(response.starts_with("HTTP/1.0 404"));
+
+    std::fs::remove_file(socket).unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+pub async fn test_unix_socket_listener() {
+    let socket = std::env::temp_dir().join(format!("checkpointq-{}-live.sock", std::process::id()));
+    let _ = std::fs::remove_file(&socket);
+
+    // a socket another server accepts connections on is not taken over
+    let live = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+    let error = Listener::Unix(socket.clone())
+        .serve(axum::Router::new())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, AppError::Listen { ref message, .. } if message.contains("another server"))
+    );
+    assert!(socket.exists());
+
+    // the socket left behind once it stopped is replaced, and removed when the server stops
+    drop(live);
+    assert!(socket.exists());
+    let server = tokio::spawn(Listener::Unix(socket.clone()).serve(axum::Router::new()));
+    let mut connected = false;
+    for _ in 0..100 {
+        if tokio::net::UnixStream::connect(&socket).await.is_ok() {
+            connected = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(connected);
+    server.abort();
+    assert!(server.await.unwrap_err().is_cancelled());
+    assert!(!socket.exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+pub async fn test_unix_socket_keeps_the_default_listener() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let socket =
+        std::env::temp_dir().join(format!("checkpointq-{}-default.sock", std::process::id()));
+    let server = CheckPointMiddleware::new(
+        server_client(vec![Ok("Hash1"), Ok("Hash1"), Ok("Hash1")]),
+        port,
+    )
+    .with_listeners(vec![Listener::Unix(socket.clone())]);
+    let server = tokio::spawn(server.serve());
+
+    let mut stream = None;
+    for _ in 0..100 {
+        if let Ok(connected) = tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+            stream = Some(connected);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let response = raw_request(stream.expect("nothing listens on 127.0.0.1"), "/health").await;
+    assert!(response.starts_with("HTTP/1.0 200"));
+    let mut unix = false;
+    for _ in 0..100 {
+        if tokio::net::UnixStream::connect(&socket).await.is_ok() {
+            unix = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(unix);
+    server.abort();
+}